@@ -53,4 +53,34 @@ pub struct Args {
     /// Hostname for CORS
     #[clap(long, value_parser)]
     pub cors_host: Option<String>,
+
+    /// Running behind a trusted reverse proxy, take the client address from X-Forwarded-For
+    #[clap(long, value_parser)]
+    #[serde(default)]
+    pub trust_proxy: bool,
+
+    /// Apply pending database migrations and exit
+    #[clap(long, value_parser)]
+    #[serde(default)]
+    pub migrate_only: bool,
+
+    /// Re-render posts rendered by an older renderer or with other Markdown options, then exit
+    #[clap(long, value_parser)]
+    #[serde(default)]
+    pub rerender: bool,
+
+    /// Re-render every post regardless of its renderer version, then exit
+    #[clap(long, value_parser)]
+    #[serde(default)]
+    pub rerender_all: bool,
+
+    /// Write a full backup (database snapshot and uploads) to the backup directory, then exit
+    #[clap(long, value_parser)]
+    #[serde(default)]
+    pub backup: bool,
+
+    /// Restore the database and uploads from a backup archive, then exit. Stop the server first
+    #[clap(long, value_parser)]
+    #[serde(default)]
+    pub restore: Option<String>,
 }
@@ -0,0 +1,93 @@
+use blog_common::dto::analytics::{AnalyticsDashboard, DailyViews, PostViews, ReferrerViews};
+use sqlx::Row;
+
+use crate::util::result::Result;
+
+const TOP_AMOUNT: i64 = 20;
+
+// 服务器本地时区的今天，例如 2022-01-31
+pub(crate) async fn today() -> Result<String> {
+    let row = sqlx::query("SELECT date('now','localtime')")
+        .fetch_one(super::get_sqlite())
+        .await?;
+    Ok(row.get(0))
+}
+
+pub(crate) async fn record_view(day: &str, post_id: i64, referrer: Option<&str>) -> Result<()> {
+    sqlx::query("INSERT INTO post_views(post_id,day,views)VALUES(?,?,1) ON CONFLICT(post_id,day) DO UPDATE SET views = views + 1")
+        .bind(post_id)
+        .bind(day)
+        .execute(super::get_sqlite())
+        .await?;
+    if let Some(domain) = referrer {
+        sqlx::query("INSERT INTO referrer_views(domain,day,views)VALUES(?,?,1) ON CONFLICT(domain,day) DO UPDATE SET views = views + 1")
+            .bind(domain)
+            .bind(day)
+            .execute(super::get_sqlite())
+            .await?;
+    }
+    Ok(())
+}
+
+// 最近 days 天（包括今天）的统计
+pub async fn dashboard(days: u32) -> Result<AnalyticsDashboard> {
+    let days = days.max(1);
+    let since = format!("-{} days", days - 1);
+
+    let rows = sqlx::query(
+        "WITH RECURSIVE d(day) AS (SELECT date('now','localtime',?) UNION ALL SELECT date(day,'+1 day') FROM d WHERE day < date('now','localtime')) \
+         SELECT d.day, IFNULL(SUM(v.views), 0) FROM d LEFT JOIN post_views v ON v.day = d.day GROUP BY d.day ORDER BY d.day",
+    )
+    .bind(&since)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    let trend: Vec<DailyViews> = rows
+        .iter()
+        .map(|r| DailyViews {
+            day: r.get(0),
+            views: r.get::<i64, usize>(1) as u64,
+        })
+        .collect();
+    let total_views = trend.iter().map(|d| d.views).sum();
+
+    let rows = sqlx::query(
+        "SELECT p.id, p.title, p.slug, SUM(v.views) AS total FROM post_views v INNER JOIN posts p ON p.id = v.post_id \
+         WHERE v.day >= date('now','localtime',?) GROUP BY p.id ORDER BY total DESC LIMIT ?",
+    )
+    .bind(&since)
+    .bind(TOP_AMOUNT)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    let top_posts = rows
+        .iter()
+        .map(|r| PostViews {
+            id: r.get(0),
+            title: r.get(1),
+            slug: r.get(2),
+            views: r.get::<i64, usize>(3) as u64,
+        })
+        .collect();
+
+    let rows = sqlx::query(
+        "SELECT domain, SUM(views) AS total FROM referrer_views WHERE day >= date('now','localtime',?) GROUP BY domain ORDER BY total DESC LIMIT ?",
+    )
+    .bind(&since)
+    .bind(TOP_AMOUNT)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    let referrers = rows
+        .iter()
+        .map(|r| ReferrerViews {
+            domain: r.get(0),
+            views: r.get::<i64, usize>(1) as u64,
+        })
+        .collect();
+
+    Ok(AnalyticsDashboard {
+        days,
+        total_views,
+        trend,
+        top_posts,
+        referrers,
+    })
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use blog_common::{
+    dto::comment::{CommentData, CommentDetail, CommentStatus},
+    result::Error,
+    util::time,
+};
+use comrak::{markdown_to_html, ComrakOptions};
+use sqlx::{Row, Sqlite};
+
+use crate::{
+    db::model::{Comment, ModerationComment},
+    util::{result::Result, snowflake},
+};
+
+const MAX_NAME_LENGTH: usize = 64;
+const MAX_EMAIL_LENGTH: usize = 128;
+const MAX_WEBSITE_LENGTH: usize = 256;
+const MAX_CONTENT_LENGTH: usize = 4096;
+
+pub(crate) fn status_to_i64(status: CommentStatus) -> i64 {
+    match status {
+        CommentStatus::Pending => 0,
+        CommentStatus::Approved => 1,
+        CommentStatus::Spam => 2,
+    }
+}
+
+pub(crate) fn parse_status(s: &str) -> Option<CommentStatus> {
+    match s {
+        "pending" => Some(CommentStatus::Pending),
+        "approved" => Some(CommentStatus::Approved),
+        "spam" => Some(CommentStatus::Spam),
+        _ => None,
+    }
+}
+
+// 评论来自匿名读者，不允许原始 HTML，危险的链接也会被 comrak 过滤掉
+fn render(markdown: &str) -> String {
+    let mut options = ComrakOptions::default();
+    options.extension.strikethrough = true;
+    options.extension.autolink = true;
+    options.render.unsafe_ = false;
+    options.render.escape = true;
+    markdown_to_html(markdown, &options)
+}
+
+fn normalize_website(website: &str) -> String {
+    let website = website.trim();
+    if website.starts_with("http://") || website.starts_with("https://") {
+        String::from(website)
+    } else {
+        String::new()
+    }
+}
+
+pub async fn add(data: CommentData, approved: bool) -> Result<(CommentStatus, CommentDetail)> {
+    let name = data.name.trim();
+    let content = data.content.trim();
+    if name.is_empty()
+        || content.is_empty()
+        || name.chars().count() > MAX_NAME_LENGTH
+        || data.email.len() > MAX_EMAIL_LENGTH
+        || data.website.len() > MAX_WEBSITE_LENGTH
+        || content.chars().count() > MAX_CONTENT_LENGTH
+    {
+        return Err(Error::BadRequest.into());
+    }
+
+    let r = sqlx::query("SELECT id FROM posts WHERE id = ?")
+        .bind(data.post_id)
+        .fetch_optional(super::get_sqlite())
+        .await?;
+    if r.is_none() {
+        return Err(Error::CannotFoundPost.into());
+    }
+
+    // 回复的是某条回复时，挂到它所在的顶层评论下
+    let parent_id = match data.parent_id {
+        Some(parent_id) if parent_id > 0 => {
+            let r = sqlx::query("SELECT parent_id FROM comments WHERE id = ? AND post_id = ?")
+                .bind(parent_id)
+                .bind(data.post_id)
+                .fetch_optional(super::get_sqlite())
+                .await?;
+            match r {
+                Some(row) => {
+                    let grandparent_id: i64 = row.get(0);
+                    if grandparent_id > 0 {
+                        grandparent_id
+                    } else {
+                        parent_id
+                    }
+                },
+                None => return Err(Error::CommentNotFound.into()),
+            }
+        },
+        _ => 0,
+    };
+
+    let status = if approved {
+        CommentStatus::Approved
+    } else {
+        CommentStatus::Pending
+    };
+    let comment = Comment {
+        id: snowflake::gen_id() as i64,
+        post_id: data.post_id,
+        parent_id,
+        name: String::from(name),
+        email: String::from(data.email.trim()),
+        website: normalize_website(&data.website),
+        markdown_content: String::from(content),
+        rendered_content: render(content),
+        status: status_to_i64(status),
+        created_at: time::unix_epoch_sec() as i64,
+    };
+    sqlx::query("INSERT INTO comments(id,post_id,parent_id,name,email,website,markdown_content,rendered_content,status,created_at)VALUES(?,?,?,?,?,?,?,?,?,?)")
+        .bind(comment.id)
+        .bind(comment.post_id)
+        .bind(comment.parent_id)
+        .bind(&comment.name)
+        .bind(&comment.email)
+        .bind(&comment.website)
+        .bind(&comment.markdown_content)
+        .bind(&comment.rendered_content)
+        .bind(comment.status)
+        .bind(comment.created_at)
+        .execute(super::get_sqlite())
+        .await?;
+    Ok((status, (&comment).into()))
+}
+
+pub async fn list(post_id: i64) -> Result<Vec<CommentDetail>> {
+    let comments = sqlx::query_as::<Sqlite, Comment>(
+        "SELECT * FROM comments WHERE post_id = ? AND status = ? ORDER BY created_at ASC",
+    )
+    .bind(post_id)
+    .bind(status_to_i64(CommentStatus::Approved))
+    .fetch_all(super::get_sqlite())
+    .await?;
+
+    let mut threads: Vec<CommentDetail> = Vec::with_capacity(comments.len());
+    let mut replies: HashMap<i64, Vec<CommentDetail>> = HashMap::new();
+    for c in comments.iter() {
+        if c.parent_id > 0 {
+            replies.entry(c.parent_id).or_insert(vec![]).push(c.into());
+        } else {
+            threads.push(c.into());
+        }
+    }
+    // 顶层评论没通过审核的话，它下面的回复也不显示
+    for thread in threads.iter_mut() {
+        if let Some(r) = replies.remove(&thread.id) {
+            thread.replies = r;
+        }
+    }
+    Ok(threads)
+}
+
+pub(crate) async fn count(post_id: i64) -> Result<u32> {
+    let row = sqlx::query("SELECT COUNT(*) FROM comments WHERE post_id = ? AND status = ?")
+        .bind(post_id)
+        .bind(status_to_i64(CommentStatus::Approved))
+        .fetch_one(super::get_sqlite())
+        .await?;
+    Ok(row.get::<i64, usize>(0) as u32)
+}
+
+pub(crate) async fn count_by_post_ids(ids: &[i64]) -> Result<HashMap<i64, u32>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut sql = String::from("SELECT post_id, COUNT(*) FROM comments WHERE status = ? AND post_id IN (");
+    for _i in 0..ids.len() {
+        sql.push_str("?,");
+    }
+    sql.replace_range(sql.len() - 1.., ") GROUP BY post_id");
+    let mut query = sqlx::query(&sql).bind(status_to_i64(CommentStatus::Approved));
+    for id in ids.iter() {
+        query = query.bind(id);
+    }
+    let rows = query.fetch_all(super::get_sqlite()).await?;
+    let mut d: HashMap<i64, u32> = HashMap::with_capacity(rows.len());
+    for row in rows {
+        d.insert(row.get(0), row.get::<i64, usize>(1) as u32);
+    }
+    Ok(d)
+}
+
+pub async fn list_by_status(status: CommentStatus) -> Result<Vec<ModerationComment>> {
+    let comments = sqlx::query_as::<Sqlite, ModerationComment>(
+        "SELECT c.id,c.post_id,p.title AS post_title,c.name,c.email,c.website,c.rendered_content,c.created_at FROM comments c INNER JOIN posts p ON p.id = c.post_id WHERE c.status = ? ORDER BY c.created_at DESC LIMIT 200",
+    )
+    .bind(status_to_i64(status))
+    .fetch_all(super::get_sqlite())
+    .await?;
+    Ok(comments)
+}
+
+pub async fn moderate(id: i64, status: CommentStatus) -> Result<()> {
+    let r = sqlx::query("UPDATE comments SET status = ? WHERE id = ?")
+        .bind(status_to_i64(status))
+        .bind(id)
+        .execute(super::get_sqlite())
+        .await?;
+    if r.rows_affected() < 1 {
+        return Err(Error::CommentNotFound.into());
+    }
+    Ok(())
+}
+
+pub async fn delete(id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM comments WHERE id = ? OR parent_id = ?")
+        .bind(id)
+        .bind(id)
+        .execute(super::get_sqlite())
+        .await?;
+    Ok(())
+}
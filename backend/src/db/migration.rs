@@ -0,0 +1,215 @@
+use std::path::Path;
+
+use blog_common::util::time;
+use sqlx::{Row, SqlitePool};
+
+use crate::util::result::Result;
+
+// 数据库结构的变更都写成新的迁移文件追加到这里，已经发布的文件不要再修改
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+    // 引入迁移之前的库（旧版 ddl.sql 建的）如果已经有这张表，就直接当作已执行
+    adopt_if_exists: Option<&'static str>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "init",
+        sql: include_str!("../resource/sql/migration/0001_init.sql"),
+        adopt_if_exists: Some("posts"),
+    },
+    Migration {
+        version: 2,
+        name: "post_slug",
+        sql: include_str!("../resource/sql/migration/0002_post_slug.sql"),
+        adopt_if_exists: Some("post_slug_history"),
+    },
+    Migration {
+        version: 3,
+        name: "post_search",
+        sql: include_str!("../resource/sql/migration/0003_post_search.sql"),
+        adopt_if_exists: Some("posts_fts"),
+    },
+    Migration {
+        version: 4,
+        name: "user",
+        sql: include_str!("../resource/sql/migration/0004_user.sql"),
+        adopt_if_exists: Some("user"),
+    },
+    Migration {
+        version: 5,
+        name: "series",
+        sql: include_str!("../resource/sql/migration/0005_series.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 6,
+        name: "tag_detail",
+        sql: include_str!("../resource/sql/migration/0006_tag_detail.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 7,
+        name: "comments",
+        sql: include_str!("../resource/sql/migration/0007_comments.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 8,
+        name: "post_summary",
+        sql: include_str!("../resource/sql/migration/0008_post_summary.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 9,
+        name: "post_views",
+        sql: include_str!("../resource/sql/migration/0009_post_views.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 10,
+        name: "post_meta",
+        sql: include_str!("../resource/sql/migration/0010_post_meta.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 11,
+        name: "post_pinned",
+        sql: include_str!("../resource/sql/migration/0011_post_pinned.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 12,
+        name: "post_toc",
+        sql: include_str!("../resource/sql/migration/0012_post_toc.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 13,
+        name: "post_render_version",
+        sql: include_str!("../resource/sql/migration/0013_post_render_version.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 14,
+        name: "post_translation",
+        sql: include_str!("../resource/sql/migration/0014_post_translation.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 15,
+        name: "post_word_count",
+        sql: include_str!("../resource/sql/migration/0015_post_word_count.sql"),
+        adopt_if_exists: None,
+    },
+    Migration {
+        version: 16,
+        name: "post_created_index",
+        sql: include_str!("../resource/sql/migration/0016_post_created_index.sql"),
+        adopt_if_exists: None,
+    },
+];
+
+async fn table_exists(pool: &SqlitePool, name: &str) -> Result<bool> {
+    let r = sqlx::query("SELECT 1 FROM sqlite_master WHERE type IN ('table','view') AND name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+    Ok(r.is_some())
+}
+
+async fn current_version(pool: &SqlitePool) -> Result<i64> {
+    let row = sqlx::query("SELECT IFNULL(MAX(version), 0) FROM schema_migrations")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get(0))
+}
+
+// 还没有迁移记录表的库按 0 处理
+pub(crate) async fn schema_version(pool: &SqlitePool) -> Result<i64> {
+    if !table_exists(pool, "schema_migrations").await? {
+        return Ok(0);
+    }
+    current_version(pool).await
+}
+
+pub(crate) fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+// 第一次启用迁移时，把旧库里已经存在的结构记录下来
+async fn adopt_legacy_schema(pool: &SqlitePool) -> Result<()> {
+    let now = time::unix_epoch_sec() as i64;
+    for m in MIGRATIONS {
+        let exists = match m.adopt_if_exists {
+            Some(table) => table_exists(pool, table).await?,
+            None => false,
+        };
+        if !exists {
+            break;
+        }
+        println!("Found existing schema for migration V{}_{}", m.version, m.name);
+        sqlx::query("INSERT INTO schema_migrations(version,name,applied_at)VALUES(?,?,?)")
+            .bind(m.version)
+            .bind(m.name)
+            .bind(now)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn backup(pool: &SqlitePool, db_path: &Path, version: i64) -> Result<()> {
+    let backup_path = db_path.with_file_name(format!(
+        "{}.v{}.{}.bak",
+        db_path.file_name().unwrap().to_string_lossy(),
+        version,
+        time::unix_epoch_sec()
+    ));
+    // VACUUM INTO 得到的是一致的快照，比直接复制文件可靠
+    sqlx::query("VACUUM INTO ?")
+        .bind(backup_path.display().to_string())
+        .execute(pool)
+        .await?;
+    println!("Backed up database to {}", backup_path.display());
+    Ok(())
+}
+
+pub(crate) async fn migrate(pool: &SqlitePool, db_path: &Path, new_database: bool) -> Result<usize> {
+    if !table_exists(pool, "schema_migrations").await? {
+        sqlx::query(
+            "CREATE TABLE schema_migrations (version INTEGER NOT NULL PRIMARY KEY, name TEXT(64) NOT NULL, applied_at INTEGER NOT NULL)",
+        )
+        .execute(pool)
+        .await?;
+        if !new_database {
+            adopt_legacy_schema(pool).await?;
+        }
+    }
+
+    let version = current_version(pool).await?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+    if !new_database {
+        backup(pool, db_path, version).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+    for m in pending.iter() {
+        println!("Applying migration V{}_{}", m.version, m.name);
+        sqlx::query(m.sql).execute(&mut tx).await?;
+        sqlx::query("INSERT INTO schema_migrations(version,name,applied_at)VALUES(?,?,?)")
+            .bind(m.version)
+            .bind(m.name)
+            .bind(time::unix_epoch_sec() as i64)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(pending.len())
+}
@@ -5,8 +5,7 @@ use std::{
     path::Path,
 };
 
-use blog_common::result::Error;
-use futures::StreamExt;
+use blog_common::{dto::post::RerenderReport, result::Error};
 use once_cell::sync::OnceCell;
 use serde::Serialize;
 use sqlx::{
@@ -19,14 +18,21 @@ use tokio::fs::{create_dir, remove_file, rename, File, OpenOptions};
 use crate::util::result::Result;
 use model::Tag;
 
+pub(crate) mod analytics;
+pub(crate) mod comment;
 pub(crate) mod management;
+mod migration;
 pub mod model;
 pub(crate) mod post;
+pub(crate) mod search;
+pub(crate) mod series;
 pub(crate) mod tag;
 pub(crate) mod user;
 
 type SqliteConnPool = sqlx::Pool<Sqlite>;
 
+pub(crate) const DATABASE_FILE: &'static str = "blog.dat";
+
 static DATA_SOURCE: OnceCell<DataSource> = OnceCell::new();
 
 // pub trait SqliteParam = for<'q> Encode<'q, Sqlite> + Type<Sqlite>;
@@ -55,7 +61,7 @@ pub fn get_sqlite<'a>() -> &'a SqliteConnPool {
 }
 
 pub async fn init_datasource() {
-    let path = Path::new(".").join("blog.dat");
+    let path = Path::new(".").join(DATABASE_FILE);
     if path.is_dir() {
         panic!("当前目录下有一个：blog.dat目录，请移动到另外一个地方再重试。");
     }
@@ -84,23 +90,6 @@ pub async fn init_datasource() {
         .await
         .expect("Init datasource failed.");
 
-    if db_file_not_exists {
-        // println!("Init database");
-        let ddl = include_str!("../resource/sql/ddl.sql");
-        // println!("ddl = {}", ddl);
-        let mut stream = sqlx::query(ddl).execute_many(&pool).await;
-        while let Some(res) = stream.next().await {
-            match res {
-                Ok(r) => println!("Initialized table"),
-                Err(e) => panic!("{:?}", e),
-            }
-        }
-        // let dml = include_str!("../resource/sql/dml.sql");
-        // if let Err(e) = sqlx::query(dml).execute(&pool).await {
-        //     panic!("{:?}", e);
-        // }
-    }
-
     let datasource = DataSource {
         sqlite: pool,
         // management: sled::open("data/management").expect("open"),
@@ -110,6 +99,21 @@ pub async fn init_datasource() {
         panic!("{:?}", e);
     }
 
+    match migration::migrate(get_sqlite(), path.as_path(), db_file_not_exists).await {
+        Ok(n) if n > 0 => println!("Applied {} migration(s)", n),
+        Ok(_) => {},
+        Err(e) => panic!("Database migration failed: {:?}", e.0),
+    }
+    if let Err(e) = search::rebuild_index_if_empty().await {
+        panic!("Rebuild search index failed: {:?}", e.0);
+    }
+    if let Err(e) = post::fill_missing_excerpts().await {
+        panic!("Fill post excerpts failed: {:?}", e.0);
+    }
+    if let Err(e) = post::fill_missing_word_counts().await {
+        panic!("Fill post word counts failed: {:?}", e.0);
+    }
+
     /*
     下面这个不会打印，解决：
     1、把map换成for_each
@@ -126,6 +130,37 @@ pub async fn init_datasource() {
     */
 }
 
+// 命令行的 --rerender 使用，每处理完一批打印一次进度
+pub async fn rerender_posts(force: bool) -> Result<RerenderReport> {
+    post::rerender(force, |r| {
+        println!("{}/{} post(s) processed", r.rendered + r.failures.len(), r.total)
+    })
+    .await
+}
+
+// 用 VACUUM INTO 得到一致的快照，服务运行时也可以执行，返回快照的数据库版本
+pub(crate) async fn snapshot(target: &Path) -> Result<i64> {
+    let pool = get_sqlite();
+    let version = migration::schema_version(pool).await?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(target.display().to_string())
+        .execute(pool)
+        .await?;
+    Ok(version)
+}
+
+pub(crate) fn latest_schema_version() -> i64 {
+    migration::latest_version()
+}
+
+// 恢复备份时，在替换之前把解压出来的数据库升级到当前版本
+pub(crate) async fn migrate_file(path: &Path) -> Result<usize> {
+    let pool = SqlitePool::connect(&format!("sqlite://{}", path.display())).await?;
+    let result = migration::migrate(&pool, path, false).await;
+    pool.close().await;
+    result
+}
+
 pub async fn shutdown() {
     let ds = DATA_SOURCE.get().unwrap();
     ds.sqlite.close().await;
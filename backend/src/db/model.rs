@@ -2,7 +2,11 @@ use core::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use blog_common::dto::{post::PostDetail, user::UserInfo};
+use blog_common::dto::{
+    comment::CommentDetail,
+    post::{PostDetail, PostMeta, TocEntry},
+    user::UserInfo,
+};
 use sqlx::{
     database::{HasArguments, HasValueRef},
     encode::IsNull,
@@ -47,6 +51,46 @@ pub struct Post {
     pub rendered_content: String,
     pub created_at: i64,
     pub updated_at: Option<i64>,
+    #[sqlx(default)]
+    pub slug: Option<String>,
+    #[sqlx(default)]
+    pub summary: String,
+    #[sqlx(default)]
+    pub excerpt: String,
+    // PostMeta 的 JSON
+    #[sqlx(default)]
+    pub meta: String,
+    #[sqlx(default)]
+    pub pinned: bool,
+    #[sqlx(default)]
+    pub featured: bool,
+    // TocEntry 列表的 JSON
+    #[sqlx(default)]
+    pub toc: String,
+    #[sqlx(default)]
+    pub language: String,
+    #[sqlx(default)]
+    pub translation_group: Option<i64>,
+    #[sqlx(default)]
+    pub word_count: i64,
+    #[sqlx(default)]
+    pub reading_time: i64,
+}
+
+impl Post {
+    pub fn meta(&self) -> PostMeta {
+        if self.meta.is_empty() {
+            return PostMeta::default();
+        }
+        serde_json::from_str(&self.meta).unwrap_or_default()
+    }
+
+    pub fn toc(&self) -> Vec<TocEntry> {
+        if self.toc.is_empty() {
+            return Vec::new();
+        }
+        serde_json::from_str(&self.toc).unwrap_or_default()
+    }
 }
 
 impl Into<PostDetail> for &Post {
@@ -60,10 +104,67 @@ impl Into<PostDetail> for &Post {
             created_at: self.created_at as u64,
             updated_at: self.updated_at.map(|t| t as u64),
             editable: false,
+            slug: self.slug.clone(),
+            series: None,
+            comment_count: 0,
+            summary: if self.summary.is_empty() {
+                None
+            } else {
+                Some(self.summary.clone())
+            },
+            meta: self.meta(),
+            pinned: self.pinned,
+            featured: self.featured,
+            toc: self.toc(),
+            translations: Vec::new(),
+            word_count: self.word_count as u32,
+            reading_time: self.reading_time as u32,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Comment {
+    pub id: i64,
+    pub post_id: i64,
+    pub parent_id: i64,
+    pub name: String,
+    pub email: String,
+    pub website: String,
+    pub markdown_content: String,
+    pub rendered_content: String,
+    pub status: i64,
+    pub created_at: i64,
+}
+
+impl Into<CommentDetail> for &Comment {
+    fn into(self) -> CommentDetail {
+        CommentDetail {
+            id: self.id,
+            post_id: self.post_id,
+            parent_id: self.parent_id,
+            name: self.name.clone(),
+            website: self.website.clone(),
+            content: self.rendered_content.clone(),
+            created_at: self.created_at as u64,
+            replies: vec![],
         }
     }
 }
 
+// 审核队列里展示用，带上博客标题
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ModerationComment {
+    pub id: i64,
+    pub post_id: i64,
+    pub post_title: String,
+    pub name: String,
+    pub email: String,
+    pub website: String,
+    pub rendered_content: String,
+    pub created_at: i64,
+}
+
 #[derive(Deserialize, Serialize, sqlx::FromRow)]
 pub struct Tag {
     pub id: i64,
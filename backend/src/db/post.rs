@@ -3,15 +3,17 @@ use std::{collections::HashMap, time::SystemTime};
 
 use blog_common::{
     dto::{
-        post::{PostData, PostDetail},
+        post::{
+            ArchiveMonth, ArchiveYear, BulkAction, BulkItemResult, PostData, PostDetail, PostMeta, RelatedPost,
+            RerenderFailure, RerenderReport, Translation,
+        },
         PaginationData,
     },
     result::Error,
     util::time,
     val,
 };
-use comrak::{markdown_to_html, ComrakOptions};
-use sqlx::{Row, Sqlite};
+use sqlx::{Connection, Executor, Row, Sqlite, SqliteConnection, Transaction};
 
 use crate::{
     db::{
@@ -21,35 +23,28 @@ use crate::{
         tag::get_names,
         SqlParam, DATA_SOURCE,
     },
+    service::render,
     util::{
-        common,
         result::{ErrorWrapper, Result},
-        snowflake,
+        slug, snowflake,
     },
 };
 
 // const START_TIME: DateTime<Utc> = Utc.ymd(1970, 1, 1).and_hms(0, 1, 1);
 
-fn review_rendered_content(c: &str) -> String {
-    let r = common::HTML_TAG_REGEX.replace_all(c, "");
-    let r = r.replace(r"\n", "").to_string();
-    if r.len() < 200 {
-        return r;
-    }
-    return r[..200].to_string();
-}
-
 async fn to_detail_list(posts: Vec<Post>) -> Result<Vec<PostDetail>> {
     if posts.is_empty() {
         return Ok(vec![]);
     }
     let post_ids: Vec<i64> = posts.iter().map(|p| p.id).collect();
+    let comment_counts = super::comment::count_by_post_ids(&post_ids).await?;
     let tags_map = tag::get_tags_by_post_ids(post_ids).await?;
     let post_detail_list = posts
         .iter()
         .map(|i| {
             let mut detail: PostDetail = i.into();
-            detail.content = review_rendered_content(&i.rendered_content);
+            detail.content = i.excerpt.clone();
+            detail.comment_count = comment_counts.get(&i.id).copied().unwrap_or(0);
             let tags = tags_map.get(&i.id);
             if tags.is_some() {
                 detail.tags = Some(tags.unwrap().iter().map(|t| t.name.clone()).collect());
@@ -67,56 +62,178 @@ async fn to_detail_list(posts: Vec<Post>) -> Result<Vec<PostDetail>> {
     Ok(post_detail_list)
 }
 
-fn append_pagination_sql(sql: &mut String, pagination_type: &str, post_id: u64) -> bool {
-    let mut order_by_asc = false;
-    if post_id > 0 {
-        let combine_word = if sql.rfind("WHERE").is_some() { "AND" } else { "WHERE" };
-        sql.push_str(combine_word);
-        if pagination_type == "prev" {
-            sql.push_str(" id>");
-            sql.push_str(post_id.to_string().as_str());
-            order_by_asc = true;
-        } else if pagination_type == "next" {
-            sql.push_str(" id<");
-            sql.push_str(post_id.to_string().as_str());
+// 列表页不需要正文，只取摘要
+const LIST_COLUMNS: &'static str = "p.id,p.title,p.title_image,'' AS markdown_content,'' AS rendered_content,p.created_at,p.updated_at,p.slug,p.excerpt,p.pinned,p.featured,p.word_count,p.reading_time";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortField {
+    Created,
+    Updated,
+    Title,
+}
+
+impl SortField {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "created" => Some(SortField::Created),
+            "updated" => Some(SortField::Updated),
+            "title" => Some(SortField::Title),
+            _ => None,
         }
     }
-    sql.push_str(" ORDER BY id ");
-    if order_by_asc {
-        sql.push_str("ASC");
-    } else {
-        sql.push_str("DESC");
+
+    // 没有修改过的博客按创建时间算
+    fn expr(&self, table: &str) -> String {
+        match self {
+            SortField::Created => format!("{}.created_at", table),
+            SortField::Updated => format!("IFNULL({0}.updated_at, {0}.created_at)", table),
+            SortField::Title => format!("{}.title", table),
+        }
     }
-    sql.push_str(" LIMIT ?");
-    order_by_asc
 }
 
-pub async fn list(pagination_type: &str, post_id: u64, page_size: u8) -> Result<PaginationData<Vec<PostDetail>>> {
-    let row = sqlx::query("SELECT COUNT(id) FROM posts")
-        .fetch_one(super::get_sqlite())
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cursor {
+    // 某篇博客之前的一页（上一页）
+    Before(i64),
+    // 某篇博客之后的一页（下一页）
+    After(i64),
+}
+
+#[derive(Clone, Debug)]
+pub struct ListOptions {
+    // 从 1 开始，有游标时忽略
+    pub page: u32,
+    pub cursor: Option<Cursor>,
+    pub page_size: u8,
+    pub sort: SortField,
+    pub asc: bool,
+    // 只保存主语言，例如 zh
+    pub language: Option<String>,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        ListOptions {
+            page: 1,
+            cursor: None,
+            page_size: val::POSTS_PAGE_SIZE,
+            sort: SortField::Created,
+            asc: false,
+            language: None,
+        }
+    }
+}
+
+// 没有设置语言的博客对所有语言都显示；只比较主语言，zh-CN 也能看到 zh、zh-TW 的博客
+const LANGUAGE_CONDITION: &'static str = " AND (p.language = '' OR lower(p.language) = ? OR lower(p.language) LIKE ?)";
+
+fn language_filter(options: &ListOptions) -> (&'static str, Vec<String>) {
+    match options.language.as_deref() {
+        Some(l) => (LANGUAGE_CONDITION, vec![String::from(l), format!("{}-%", l)]),
+        None => ("", vec![]),
+    }
+}
+
+// 所有值都通过参数绑定，这里只拼接固定的列名和排序方向
+// 返回的 bool 表示查出来的数据需要倒过来
+fn append_pagination_sql(sql: &mut String, options: &ListOptions) -> bool {
+    let expr = options.sort.expr("p");
+    let reverse = matches!(options.cursor, Some(Cursor::Before(_)));
+    let asc = options.asc != reverse;
+    if options.cursor.is_some() {
+        let combine_word = if sql.rfind("WHERE").is_some() { " AND" } else { " WHERE" };
+        sql.push_str(combine_word);
+        // 排序字段可能重复，加上 id 保证顺序稳定
+        sql.push_str(&format!(
+            " ({}, p.id) {} (SELECT {}, c.id FROM posts c WHERE c.id = ?)",
+            expr,
+            if asc { ">" } else { "<" },
+            options.sort.expr("c")
+        ));
+    }
+    let direction = if asc { "ASC" } else { "DESC" };
+    sql.push_str(&format!(
+        " ORDER BY {0} {1}, p.id {1} LIMIT ? OFFSET ?",
+        expr, direction
+    ));
+    reverse
+}
+
+async fn query_list(sql: &str, params: Vec<SqlParam>, options: &ListOptions) -> Result<Vec<Post>> {
+    let mut sql = String::from(sql);
+    let reverse = append_pagination_sql(&mut sql, options);
+    let mut query = sqlx::query_as::<Sqlite, Post>(&sql);
+    for p in params {
+        query = match p {
+            SqlParam::I8(v) => query.bind(v),
+            SqlParam::I16(v) => query.bind(v),
+            SqlParam::I32(v) => query.bind(v),
+            SqlParam::I64(v) => query.bind(v),
+            SqlParam::STRING(v) => query.bind(v),
+        };
+    }
+    let offset = match options.cursor {
+        Some(Cursor::Before(id)) | Some(Cursor::After(id)) => {
+            query = query.bind(id);
+            0
+        },
+        None => options.page.max(1).saturating_sub(1) as i64 * options.page_size as i64,
+    };
+    let mut d = query
+        .bind(options.page_size)
+        .bind(offset)
+        .fetch_all(super::get_sqlite())
         .await?;
+    if reverse {
+        d.reverse();
+    }
+    Ok(d)
+}
+
+pub async fn list(options: &ListOptions) -> Result<PaginationData<Vec<PostDetail>>> {
+    // 置顶的博客不参与分页，只在第一页的最前面显示，这样页码和游标都不受影响
+    let (language_condition, language_params) = language_filter(options);
+    let sql = format!(
+        "SELECT COUNT(p.id), IFNULL(SUM(p.pinned), 0) FROM posts p WHERE 1 = 1{}",
+        language_condition
+    );
+    let mut query = sqlx::query(&sql);
+    for p in language_params.iter() {
+        query = query.bind(p);
+    }
+    let row = query.fetch_one(super::get_sqlite()).await?;
     let total: i64 = row.get(0);
+    let pinned_total: i64 = row.get(1);
     // println!("total={}", total);
     if total < 1 {
         return Ok(PaginationData { total: 0, data: vec![] });
     }
 
-    let mut sql = String::with_capacity(256);
-    sql.push_str(
-        "SELECT id,title,title_image,'' AS markdown_content,'' AS rendered_content,created_at,updated_at FROM posts ",
-    );
-    let order_by_asc = append_pagination_sql(&mut sql, pagination_type, post_id);
-    println!("sql={}", sql);
-
-    let mut d = sqlx::query_as::<Sqlite, Post>(&sql)
-        .bind(page_size)
-        .fetch_all(super::get_sqlite())
-        .await?;
-    if order_by_asc {
-        d.reverse();
+    let mut d = query_list(
+        &format!(
+            "SELECT {} FROM posts p WHERE p.pinned = 0{}",
+            LIST_COLUMNS, language_condition
+        ),
+        language_params.iter().cloned().map(SqlParam::STRING).collect(),
+        options,
+    )
+    .await?;
+    if pinned_total > 0 && options.page <= 1 && options.cursor.is_none() {
+        let sql = format!(
+            "SELECT {} FROM posts p WHERE p.pinned = 1{} ORDER BY p.id DESC",
+            LIST_COLUMNS, language_condition
+        );
+        let mut query = sqlx::query_as::<Sqlite, Post>(&sql);
+        for p in language_params.iter() {
+            query = query.bind(p);
+        }
+        let mut pinned = query.fetch_all(super::get_sqlite()).await?;
+        pinned.append(&mut d);
+        d = pinned;
     }
     Ok(PaginationData {
-        total: total as u64,
+        total: (total - pinned_total) as u64,
         data: to_detail_list(d).await?,
     })
     /*
@@ -130,12 +247,7 @@ pub async fn list(pagination_type: &str, post_id: u64, page_size: u8) -> Result<
     */
 }
 
-pub async fn list_by_tag(
-    tag_name: String,
-    pagination_type: &str,
-    post_id: u64,
-    page_size: u8,
-) -> Result<PaginationData<Vec<PostDetail>>> {
+pub async fn list_by_tag(tag_name: String, options: &ListOptions) -> Result<PaginationData<Vec<PostDetail>>> {
     let tag_name = urlencoding::decode(&tag_name)?;
     let s = tag_name.as_ref();
     let tag = sqlx::query_as::<Sqlite, Tag>("SELECT id,name FROM tags WHERE name = ?")
@@ -147,10 +259,16 @@ pub async fn list_by_tag(
     }
     let tag = tag.unwrap();
 
-    let r = sqlx::query("SELECT COUNT(*) FROM tags_usage WHERE tag_id = ?")
-        .bind(tag.id)
-        .fetch_one(super::get_sqlite())
-        .await?;
+    let (language_condition, language_params) = language_filter(options);
+    let sql = format!(
+        "SELECT COUNT(*) FROM tags_usage u INNER JOIN posts p ON p.id = u.post_id WHERE u.tag_id = ?{}",
+        language_condition
+    );
+    let mut query = sqlx::query(&sql).bind(tag.id);
+    for p in language_params.iter() {
+        query = query.bind(p);
+    }
+    let r = query.fetch_one(super::get_sqlite()).await?;
     let r = r.try_get::<i64, usize>(0);
     if let Err(e) = r {
         eprintln!("{:?}", e);
@@ -162,28 +280,145 @@ pub async fn list_by_tag(
         return Ok(PaginationData { total: 0, data: vec![] });
     }
 
-    let mut sql = String::with_capacity(256);
-    sql.push_str("SELECT id,title,title_image,'' AS markdown_content,rendered_content,created_at,updated_at FROM posts WHERE id IN (SELECT post_id FROM tags_usage WHERE tag_id = ? ");
-    let order_by_asc = append_pagination_sql(&mut sql, pagination_type, post_id);
-    sql.push_str(")");
-    println!("sql={}", sql);
-    let mut d = sqlx::query_as::<Sqlite, Post>(
-        // "SELECT id,title,title_image,'' AS markdown_content,rendered_content,created_at,updated_at FROM posts WHERE id IN (SELECT post_id FROM tags_usage WHERE tag_id = ? ORDER BY id DESC LIMIT ?, ?)",
-        &sql,
+    let mut params = vec![SqlParam::I64(tag.id)];
+    params.extend(language_params.into_iter().map(SqlParam::STRING));
+    let d = query_list(
+        &format!(
+            "SELECT {} FROM posts p WHERE p.id IN (SELECT post_id FROM tags_usage WHERE tag_id = ?){}",
+            LIST_COLUMNS, language_condition
+        ),
+        params,
+        options,
     )
-    .bind(tag.id)
-    .bind(page_size)
+    .await?;
+    Ok(PaginationData {
+        total: total as u64,
+        data: to_detail_list(d).await?,
+    })
+}
+
+// 按服务器本地时区统计
+const ARCHIVE_MONTH_EXPR: &'static str = "strftime('%Y-%m', created_at, 'unixepoch', 'localtime')";
+
+pub async fn archive() -> Result<Vec<ArchiveYear>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} AS m, COUNT(*) FROM posts GROUP BY m ORDER BY m DESC",
+        ARCHIVE_MONTH_EXPR
+    ))
     .fetch_all(super::get_sqlite())
     .await?;
-    if order_by_asc {
-        d.reverse();
+    let mut years: Vec<ArchiveYear> = Vec::new();
+    for row in rows.iter() {
+        let period: String = row.get(0);
+        let count = row.get::<i64, usize>(1) as u32;
+        let (year, month) = match period.split_once('-') {
+            Some((y, m)) => (y.parse::<i32>().unwrap_or(0), m.parse::<u32>().unwrap_or(0)),
+            None => continue,
+        };
+        match years.last_mut() {
+            Some(y) if y.year == year => {
+                y.count += count;
+                y.months.push(ArchiveMonth { month, count });
+            },
+            _ => years.push(ArchiveYear {
+                year,
+                count,
+                months: vec![ArchiveMonth { month, count }],
+            }),
+        }
     }
+    Ok(years)
+}
+
+// 本地时区某月的 [开始, 结束) 时间戳，夏令时交给 SQLite 按本地时区换算
+async fn month_range(year: i32, month: u32) -> Result<(i64, i64)> {
+    if month < 1 || month > 12 || year < 1 || year > 9998 {
+        return Err(Error::BadRequest.into());
+    }
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    let row =
+        sqlx::query("SELECT CAST(strftime('%s', ?, 'utc') AS INTEGER), CAST(strftime('%s', ?, 'utc') AS INTEGER)")
+            .bind(format!("{:04}-{:02}-01", year, month))
+            .bind(format!("{:04}-{:02}-01", next_year, next_month))
+            .fetch_one(super::get_sqlite())
+            .await?;
+    Ok((row.get(0), row.get(1)))
+}
+
+pub async fn list_by_month(year: i32, month: u32, options: &ListOptions) -> Result<PaginationData<Vec<PostDetail>>> {
+    let (start, end) = month_range(year, month).await?;
+    let (language_condition, language_params) = language_filter(options);
+    let sql = format!(
+        "SELECT COUNT(*) FROM posts p WHERE created_at >= ? AND created_at < ?{}",
+        language_condition
+    );
+    let mut query = sqlx::query(&sql).bind(start).bind(end);
+    for p in language_params.iter() {
+        query = query.bind(p);
+    }
+    let row = query.fetch_one(super::get_sqlite()).await?;
+    let total: i64 = row.get(0);
+    if total < 1 {
+        return Ok(PaginationData { total: 0, data: vec![] });
+    }
+
+    let mut params = vec![SqlParam::I64(start), SqlParam::I64(end)];
+    params.extend(language_params.into_iter().map(SqlParam::STRING));
+    let d = query_list(
+        &format!(
+            "SELECT {} FROM posts p WHERE created_at >= ? AND created_at < ?{}",
+            LIST_COLUMNS, language_condition
+        ),
+        params,
+        options,
+    )
+    .await?;
     Ok(PaginationData {
         total: total as u64,
         data: to_detail_list(d).await?,
     })
 }
 
+const MAX_FEATURED_POSTS: u8 = 5;
+
+pub async fn featured() -> Result<Vec<PostDetail>> {
+    let d = sqlx::query_as::<Sqlite, Post>(&format!(
+        "SELECT {} FROM posts p WHERE p.featured = 1 ORDER BY p.id DESC LIMIT ?",
+        LIST_COLUMNS
+    ))
+    .bind(MAX_FEATURED_POSTS)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    to_detail_list(d).await
+}
+
+pub const MAX_RELATED_POSTS: u8 = 10;
+
+// 每个共同标签记 1 分，再加上这个标签的稀有度（用的博客越少越高），分数相同时新的在前
+pub async fn related(id: i64, amount: u8) -> Result<Vec<RelatedPost>> {
+    let rows = sqlx::query(
+        "SELECT p.id, p.title, p.title_image, p.slug, SUM(1.0 + 1.0 / w.amount) AS score FROM tags_usage u \
+         INNER JOIN tags_usage o ON o.tag_id = u.tag_id AND o.post_id <> u.post_id \
+         INNER JOIN (SELECT tag_id, COUNT(*) AS amount FROM tags_usage WHERE tag_id IN (SELECT tag_id FROM tags_usage WHERE post_id = ?) GROUP BY tag_id) w ON w.tag_id = u.tag_id \
+         INNER JOIN posts p ON p.id = o.post_id \
+         WHERE u.post_id = ? GROUP BY p.id ORDER BY score DESC, p.created_at DESC LIMIT ?",
+    )
+    .bind(id)
+    .bind(id)
+    .bind(amount.min(MAX_RELATED_POSTS))
+    .fetch_all(super::get_sqlite())
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| RelatedPost {
+            id: r.get(0),
+            title: r.get(1),
+            title_image: r.get(2),
+            slug: r.get(3),
+        })
+        .collect())
+}
+
 pub async fn new_post() -> Result<i64> {
     let id = snowflake::gen_id() as i64;
     let last_insert_rowid =
@@ -202,6 +437,16 @@ pub async fn new_post() -> Result<i64> {
     Ok(id)
 }
 
+// 导入时保留原来的发布时间
+pub(crate) async fn set_created_at(id: i64, created_at: i64) -> Result<()> {
+    sqlx::query("UPDATE posts SET created_at=? WHERE id=?")
+        .bind(created_at)
+        .bind(id)
+        .execute(super::get_sqlite())
+        .await?;
+    Ok(())
+}
+
 pub async fn update_title_image(id: i64, title_image: &str) -> Result<()> {
     sqlx::query("UPDATE posts SET title_image=? WHERE id=?")
         .bind(title_image)
@@ -214,9 +459,9 @@ pub async fn update_title_image(id: i64, title_image: &str) -> Result<()> {
 
 async fn get_post(id: i64, edit: bool) -> Result<Option<Post>> {
     let sql = if edit {
-        "SELECT id,title,title_image,'' AS markdown_content,markdown_content AS rendered_content,created_at,updated_at FROM posts WHERE id = ?"
+        "SELECT id,title,title_image,'' AS markdown_content,markdown_content AS rendered_content,created_at,updated_at,slug,summary,meta,pinned,featured,toc,language,translation_group,word_count,reading_time FROM posts WHERE id = ?"
     } else {
-        "SELECT id,title,title_image,'' AS markdown_content,rendered_content,created_at,updated_at FROM posts WHERE id = ?"
+        "SELECT id,title,title_image,'' AS markdown_content,rendered_content,created_at,updated_at,slug,summary,meta,pinned,featured,toc,language,translation_group,word_count,reading_time FROM posts WHERE id = ?"
     };
     sqlx::query_as::<Sqlite, Post>(sql)
         .bind(id)
@@ -228,35 +473,336 @@ async fn get_post(id: i64, edit: bool) -> Result<Option<Post>> {
         })
 }
 
-pub async fn save(post_data: PostData) -> Result<PostDetail> {
+// 找一个没有被其它博客占用（包括历史 slug）的 slug，冲突时追加序号
+async fn unique_slug(connection: &mut SqliteConnection, post_id: i64, base: &str) -> Result<String> {
+    let mut candidate = String::from(base);
+    let mut sequence = 1;
+    loop {
+        let taken = sqlx::query(
+            "SELECT id FROM posts WHERE slug = ? AND id <> ? UNION SELECT post_id FROM post_slug_history WHERE slug = ? AND post_id <> ?",
+        )
+        .bind(&candidate)
+        .bind(post_id)
+        .bind(&candidate)
+        .bind(post_id)
+        .fetch_optional(&mut *connection)
+        .await?;
+        if taken.is_none() {
+            return Ok(candidate);
+        }
+        sequence += 1;
+        candidate = format!("{}-{}", base, sequence);
+    }
+}
+
+async fn resolve_slug(
+    connection: &mut SqliteConnection,
+    post_id: i64,
+    wanted: Option<&str>,
+    title: &str,
+    current: Option<&str>,
+) -> Result<String> {
+    let wanted = wanted.map(slug::slugify).unwrap_or_default();
+    let base = if !wanted.is_empty() {
+        wanted
+    } else if let Some(current) = current {
+        // 已经有永久链接了，不随标题变化
+        return Ok(String::from(current));
+    } else if title.is_empty() || title.eq(val::DEFAULT_POST_TITLE) {
+        slug::fallback(post_id)
+    } else {
+        let s = slug::slugify(title);
+        if s.is_empty() {
+            slug::fallback(post_id)
+        } else {
+            s
+        }
+    };
+    unique_slug(connection, post_id, &base).await
+}
+
+async fn update_slug(
+    connection: &mut SqliteConnection,
+    post_id: i64,
+    old_slug: Option<&str>,
+    new_slug: &str,
+) -> Result<()> {
+    if old_slug.map_or(false, |s| s.eq(new_slug)) {
+        return Ok(());
+    }
+    // 改回了以前用过的 slug
+    sqlx::query("DELETE FROM post_slug_history WHERE slug = ?")
+        .bind(new_slug)
+        .execute(&mut *connection)
+        .await?;
+    sqlx::query("UPDATE posts SET slug=? WHERE id=?")
+        .bind(new_slug)
+        .bind(post_id)
+        .execute(&mut *connection)
+        .await?;
+    if let Some(old_slug) = old_slug {
+        sqlx::query("REPLACE INTO post_slug_history(slug, post_id, created_at)VALUES(?,?,?)")
+            .bind(old_slug)
+            .bind(post_id)
+            .bind(time::unix_epoch_sec() as i64)
+            .execute(&mut *connection)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn find_id_by_slug(slug: &str) -> Result<Option<i64>> {
+    let r = sqlx::query("SELECT id FROM posts WHERE slug = ?")
+        .bind(slug)
+        .fetch_optional(super::get_sqlite())
+        .await?;
+    Ok(r.map(|row| row.get(0)))
+}
+
+/// 旧的 slug 对应的当前 slug，用于 301 跳转
+pub async fn find_moved_slug(slug: &str) -> Result<Option<String>> {
+    let r = sqlx::query(
+        "SELECT p.slug FROM post_slug_history h INNER JOIN posts p ON h.post_id = p.id WHERE h.slug = ? AND p.slug IS NOT NULL",
+    )
+    .bind(slug)
+    .fetch_optional(super::get_sqlite())
+    .await?;
+    Ok(r.map(|row| row.get(0)))
+}
+
+pub(crate) async fn translations(id: i64, group: Option<i64>) -> Result<Vec<Translation>> {
+    let group = match group {
+        Some(g) => g,
+        None => return Ok(vec![]),
+    };
+    let rows = sqlx::query(
+        "SELECT id, language, title, slug FROM posts WHERE translation_group = ? AND id <> ? ORDER BY language",
+    )
+    .bind(group)
+    .bind(id)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| Translation {
+            id: r.get(0),
+            language: r.get(1),
+            title: r.get(2),
+            slug: r.get(3),
+        })
+        .collect())
+}
+
+// 组里只剩一篇时就不算翻译了
+async fn dissolve_translation_group<'e, E>(executor: E, group: i64) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(
+        "UPDATE posts SET translation_group = NULL WHERE translation_group = ? AND (SELECT COUNT(*) FROM posts WHERE translation_group = ?) < 2",
+    )
+    .bind(group)
+    .bind(group)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// 加入 target 所在的翻译组，target 还没有组时新建一个；同一组里每种语言只能有一篇
+async fn resolve_translation_group(
+    connection: &mut SqliteConnection,
+    post_id: i64,
+    language: &str,
+    target: Option<i64>,
+) -> Result<Option<i64>> {
+    let target = match target.filter(|t| *t != post_id) {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    if language.is_empty() {
+        return Err(Error::BusinessException(String::from(
+            "关联翻译前需要先填写语言/Set the language before linking a translation",
+        ))
+        .into());
+    }
+    let row = sqlx::query("SELECT translation_group, language FROM posts WHERE id = ?")
+        .bind(target)
+        .fetch_optional(&mut *connection)
+        .await?;
+    let (group, target_language): (Option<i64>, String) = match row {
+        Some(r) => (r.get(0), r.get(1)),
+        None => return Err(Error::CannotFoundPost.into()),
+    };
+    let duplicated = match group {
+        Some(g) => sqlx::query("SELECT id FROM posts WHERE translation_group = ? AND id <> ? AND lower(language) = ?")
+            .bind(g)
+            .bind(post_id)
+            .bind(language.to_lowercase())
+            .fetch_optional(&mut *connection)
+            .await?
+            .is_some(),
+        None => target_language.eq_ignore_ascii_case(language),
+    };
+    if duplicated {
+        return Err(Error::BusinessException(format!(
+            "这组翻译里已经有 {0} 的版本/This translation group already has a {0} version",
+            language
+        ))
+        .into());
+    }
+    let group = match group {
+        Some(g) => g,
+        None => {
+            // 不用博客的 id，不然那篇博客退出后再关联会连到旧的组
+            let g = snowflake::gen_id() as i64;
+            sqlx::query("UPDATE posts SET translation_group = ? WHERE id = ?")
+                .bind(g)
+                .bind(target)
+                .execute(&mut *connection)
+                .await?;
+            g
+        },
+    };
+    Ok(Some(group))
+}
+
+const MAX_META_EXTRA_FIELDS: usize = 32;
+const MAX_META_VALUE_LENGTH: usize = 1024;
+// 导出 Hugo 时已经会写的 front matter 字段，自定义字段不能重名
+const RESERVED_META_KEYS: [&'static str; 11] = [
+    "title",
+    "date",
+    "draft",
+    "slug",
+    "description",
+    "canonicalURL",
+    "author",
+    "language",
+    "lang",
+    "translationKey",
+    "imageAlt",
+];
+const RERENDER_BATCH_SIZE: usize = 50;
+
+// 自定义字段名会写进 front matter，只允许字母、数字、下划线和中划线
+fn check_meta(meta: &mut PostMeta) -> Result<()> {
+    meta.description = String::from(meta.description.trim());
+    meta.canonical_url = String::from(meta.canonical_url.trim());
+    meta.title_image_alt = String::from(meta.title_image_alt.trim());
+    meta.author = String::from(meta.author.trim());
+    meta.language = String::from(meta.language.trim());
+    meta.extra = std::mem::take(&mut meta.extra)
+        .into_iter()
+        .map(|(k, v)| (String::from(k.trim()), String::from(v.trim())))
+        .filter(|(k, _)| !k.is_empty())
+        .collect();
+
+    let too_long = [
+        &meta.description,
+        &meta.canonical_url,
+        &meta.title_image_alt,
+        &meta.author,
+    ]
+    .into_iter()
+    .chain(meta.extra.values())
+    .any(|v| v.chars().count() > MAX_META_VALUE_LENGTH);
+    let invalid_url = !meta.canonical_url.is_empty()
+        && !meta.canonical_url.starts_with("http://")
+        && !meta.canonical_url.starts_with("https://");
+    let invalid_language =
+        meta.language.len() > 35 || !meta.language.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let invalid_key = meta.extra.len() > MAX_META_EXTRA_FIELDS
+        || meta
+            .extra
+            .keys()
+            .any(|k| k.len() > 64 || !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        || meta.extra.keys().any(|k| RESERVED_META_KEYS.contains(&k.as_str()));
+    if too_long || invalid_url || invalid_language || invalid_key {
+        return Err(Error::BadRequest.into());
+    }
+    Ok(())
+}
+
+pub async fn save(mut post_data: PostData) -> Result<PostDetail> {
+    check_meta(&mut post_data.meta)?;
     let post = get_post(post_data.id, true).await?;
     if post.is_none() {
         return Err(Error::CannotFoundPost.into());
     }
 
-    // needs to be in a transaction
-    let transaction = super::get_sqlite().begin().await?;
+    let post = post.unwrap();
 
-    if post_data.tags.is_some() {
-        super::tag::record_usage(post_data.id, post_data.tags.as_ref().unwrap()).await?;
-    }
+    // 先渲染，渲染失败时什么都不改
+    let extensions = render::load_extensions().await?;
+    let rendered = render::render_markdown(&post_data.content, &extensions)?;
+
+    // 标签、永久链接、系列、翻译、正文和全文索引要么都保存，要么都不保存
+    let mut transaction = super::get_sqlite().begin().await?;
+
+    // 没有标签时也要清理掉原来的关联
+    super::tag::record_usage(
+        &mut transaction,
+        post_data.id,
+        post_data.tags.as_deref().unwrap_or_default(),
+    )
+    .await?;
 
     // let parser = pulldown_cmark::Parser::new(body);
     // let mut html_text = String::new();
     // pulldown_cmark::html::push_html(&mut html_text, parser);
 
-    let post = post.unwrap();
+    let slug = resolve_slug(
+        &mut transaction,
+        post_data.id,
+        post_data.slug.as_deref(),
+        &post_data.title,
+        post.slug.as_deref(),
+    )
+    .await?;
+    update_slug(&mut transaction, post_data.id, post.slug.as_deref(), &slug).await?;
+    super::series::assign(
+        &mut transaction,
+        post_data.id,
+        post_data.series.as_deref(),
+        post_data.series_position,
+    )
+    .await?;
+    let translation_group = resolve_translation_group(
+        &mut transaction,
+        post_data.id,
+        &post_data.meta.language,
+        post_data.translation_of,
+    )
+    .await?;
 
-    let post_detail = PostDetail {
+    let word_count = render::word_count(&post_data.content);
+    let mut post_detail = PostDetail {
         id: post_data.id,
         title: post_data.title,
         title_image: post_data.title_image,
-        content: markdown_to_html(&post_data.content, &ComrakOptions::default()),
+        content: rendered.html,
         tags: post_data.tags,
         created_at: post.created_at as u64,
         updated_at: post.updated_at.map(|time| time as u64),
         editable: true,
+        slug: Some(slug),
+        series: None,
+        comment_count: 0,
+        summary: post_data
+            .summary
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from),
+        meta: post_data.meta,
+        pinned: post_data.pinned,
+        featured: post_data.featured,
+        toc: rendered.toc,
+        translations: Vec::new(),
+        word_count: word_count.words,
+        reading_time: word_count.reading_time,
     };
+    let excerpt = render::excerpt(&post_data.content, post_detail.summary.as_deref());
 
     let post_title = if post_detail.title.is_empty() {
         val::DEFAULT_POST_TITLE
@@ -266,24 +812,147 @@ pub async fn save(post_data: PostData) -> Result<PostDetail> {
 
     // save to sqlite
     sqlx::query(
-        "UPDATE posts SET title=?, title_image=?, markdown_content=?, rendered_content=?, updated_at=? WHERE id=?",
+        "UPDATE posts SET title=?, title_image=?, markdown_content=?, rendered_content=?, summary=?, excerpt=?, meta=?, pinned=?, featured=?, toc=?, render_version=?, language=?, translation_group=?, word_count=?, reading_time=?, updated_at=? WHERE id=?",
     )
     .bind(post_title)
     .bind(&post_detail.title_image)
     .bind(&post_data.content)
     .bind(&post_detail.content)
+    .bind(post_detail.summary.as_deref().unwrap_or_default())
+    .bind(&excerpt)
+    .bind(serde_json::to_string(&post_detail.meta)?)
+    .bind(post_detail.pinned)
+    .bind(post_detail.featured)
+    .bind(serde_json::to_string(&post_detail.toc)?)
+    .bind(render::render_version(&extensions))
+    .bind(&post_detail.meta.language)
+    .bind(translation_group)
+    .bind(post_detail.word_count)
+    .bind(post_detail.reading_time)
     .bind(time::unix_epoch_sec() as i64)
     .bind(&post_detail.id)
-    .execute(super::get_sqlite())
+    .execute(&mut transaction)
     .await?;
 
+    super::search::index_post(&mut transaction, post_detail.id, post_title, &post_data.content).await?;
+
+    // 离开原来的翻译组后，组里只剩一篇时解散
+    if let Some(old_group) = post.translation_group.filter(|g| translation_group != Some(*g)) {
+        dissolve_translation_group(&mut transaction, old_group).await?;
+    }
+
     // 这里只关心 commit，因为 https://docs.rs/sqlx/0.5.1/sqlx/struct.Transaction.html 说到
     // If neither are called before the transaction goes out-of-scope, rollback is called. In other words, rollback is called on drop if the transaction is still in-progress.
     transaction.commit().await?;
 
+    post_detail.series = super::series::of_post(post_detail.id).await?;
+    post_detail.comment_count = super::comment::count(post_detail.id).await?;
+    post_detail.translations = translations(post_detail.id, translation_group).await?;
     Ok(post_detail)
 }
 
+// 摘要字段是后来加的，旧博客在启动时补上
+pub(crate) async fn fill_missing_excerpts() -> Result<()> {
+    let rows =
+        sqlx::query("SELECT id, markdown_content, summary FROM posts WHERE excerpt = '' AND markdown_content <> ''")
+            .fetch_all(super::get_sqlite())
+            .await?;
+    for row in rows.iter() {
+        let id: i64 = row.get(0);
+        let markdown: String = row.get(1);
+        let summary: String = row.get(2);
+        sqlx::query("UPDATE posts SET excerpt = ? WHERE id = ?")
+            .bind(render::excerpt(&markdown, Some(&summary)))
+            .bind(id)
+            .execute(super::get_sqlite())
+            .await?;
+    }
+    Ok(())
+}
+
+// 字数是后来加的，旧博客在启动时补上
+pub(crate) async fn fill_missing_word_counts() -> Result<()> {
+    let rows = sqlx::query("SELECT id, markdown_content FROM posts WHERE reading_time = 0 AND markdown_content <> ''")
+        .fetch_all(super::get_sqlite())
+        .await?;
+    for row in rows.iter() {
+        let id: i64 = row.get(0);
+        let markdown: String = row.get(1);
+        let count = render::word_count(&markdown);
+        sqlx::query("UPDATE posts SET word_count = ?, reading_time = ? WHERE id = ?")
+            .bind(count.words)
+            .bind(count.reading_time)
+            .bind(id)
+            .execute(super::get_sqlite())
+            .await?;
+    }
+    Ok(())
+}
+
+/// 用当前的渲染配置重新渲染 render_version 过期的博客，force 为 true 时全部重新渲染
+pub(crate) async fn rerender<P>(force: bool, progress: P) -> Result<RerenderReport>
+where
+    P: Fn(&RerenderReport),
+{
+    let extensions = render::load_extensions().await?;
+    let version = render::render_version(&extensions);
+    let ids: Vec<i64> = if force {
+        sqlx::query("SELECT id FROM posts ORDER BY id")
+            .fetch_all(super::get_sqlite())
+            .await?
+    } else {
+        sqlx::query("SELECT id FROM posts WHERE render_version <> ? ORDER BY id")
+            .bind(&version)
+            .fetch_all(super::get_sqlite())
+            .await?
+    }
+    .iter()
+    .map(|row| row.get(0))
+    .collect();
+
+    let mut report = RerenderReport {
+        total: ids.len(),
+        ..Default::default()
+    };
+    for batch in ids.chunks(RERENDER_BATCH_SIZE) {
+        let mut transaction = super::get_sqlite().begin().await?;
+        for id in batch {
+            let row = sqlx::query("SELECT title, markdown_content, summary FROM posts WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut transaction)
+                .await?;
+            let title: String = row.get(0);
+            let markdown: String = row.get(1);
+            let summary: String = row.get(2);
+            // 渲染出错只记录下来，不影响其它博客
+            let rendered = match render::render_markdown(&markdown, &extensions) {
+                Ok(r) => r,
+                Err(e) => {
+                    report.failures.push(RerenderFailure {
+                        id: *id,
+                        title,
+                        error: e.0.to_string(),
+                    });
+                    continue;
+                },
+            };
+            let excerpt = render::excerpt(&markdown, Some(&summary));
+            sqlx::query("UPDATE posts SET rendered_content = ?, excerpt = ?, toc = ?, render_version = ? WHERE id = ?")
+                .bind(&rendered.html)
+                .bind(&excerpt)
+                .bind(serde_json::to_string(&rendered.toc)?)
+                .bind(&version)
+                .bind(id)
+                .execute(&mut transaction)
+                .await?;
+            report.rendered += 1;
+        }
+        transaction.commit().await?;
+        progress(&report);
+    }
+    Ok(report)
+}
+
 pub async fn show(id: u64, editable: bool) -> Result<PostDetail> {
     // let r: Option<PostDetail> = db::sled_get(&DATA_SOURCE.get().unwrap().post, id.to_le_bytes()).await?;
     let id = id as i64;
@@ -295,17 +964,51 @@ pub async fn show(id: u64, editable: bool) -> Result<PostDetail> {
             .bind(id)
             .fetch_all(super::get_sqlite())
             .await?.iter().map(|t| t.name.clone()).collect();
-        let mut post_detail: PostDetail = (&r.unwrap()).into();
+        let post = r.unwrap();
+        let mut post_detail: PostDetail = (&post).into();
         post_detail.tags = Some(tags);
+        post_detail.series = super::series::of_post(id).await?;
+        post_detail.comment_count = super::comment::count(id).await?;
+        post_detail.translations = translations(id, post.translation_group).await?;
         Ok(post_detail)
     }
 }
 
-pub async fn delete(id: u64) -> Result<()> {
-    let r = sqlx::query("DELETE FROM posts WHERE id=?")
-        .bind(id as i64)
-        .execute(super::get_sqlite())
+// 博客和它的地址历史、索引、系列、标签、评论、阅读统计
+const DELETE_POST_SQL: [&'static str; 7] = [
+    "DELETE FROM posts WHERE id = ?",
+    "DELETE FROM post_slug_history WHERE post_id = ?",
+    "DELETE FROM posts_fts WHERE rowid = ?",
+    "DELETE FROM series_posts WHERE post_id = ?",
+    "DELETE FROM tags_usage WHERE post_id = ?",
+    "DELETE FROM comments WHERE post_id = ?",
+    "DELETE FROM post_views WHERE post_id = ?",
+];
+
+// 单篇删除和批量删除共用；没有用到的标签由调用方清理
+async fn delete_post(connection: &mut SqliteConnection, id: i64) -> Result<()> {
+    let row = sqlx::query("SELECT translation_group FROM posts WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *connection)
         .await?;
+    let translation_group: Option<i64> = match row {
+        Some(r) => r.get(0),
+        None => return Err(Error::CannotFoundPost.into()),
+    };
+    for sql in DELETE_POST_SQL.iter() {
+        sqlx::query(sql).bind(id).execute(&mut *connection).await?;
+    }
+    if let Some(group) = translation_group {
+        dissolve_translation_group(&mut *connection, group).await?;
+    }
+    Ok(())
+}
+
+pub async fn delete(id: u64) -> Result<()> {
+    let mut transaction = super::get_sqlite().begin().await?;
+    delete_post(&mut transaction, id as i64).await?;
+    sqlx::query(tag::REMOVE_ORPHANS_SQL).execute(&mut transaction).await?;
+    transaction.commit().await?;
     Ok(())
 }
 
@@ -316,6 +1019,203 @@ pub async fn all() -> Result<Vec<Post>> {
     Ok(posts)
 }
 
+// 导出电子书用，按发表时间从早到晚排列；to 不包含在内
+pub(crate) async fn list_for_export(tag: Option<&str>, from: Option<i64>, to: Option<i64>) -> Result<Vec<Post>> {
+    // 新建后还没保存过的博客没有标题
+    let mut sql = String::from("SELECT * FROM posts WHERE title != ''");
+    if tag.is_some() {
+        sql.push_str(
+            " AND id IN (SELECT u.post_id FROM tags_usage u INNER JOIN tags t ON t.id = u.tag_id WHERE t.name = ?)",
+        );
+    }
+    if from.is_some() {
+        sql.push_str(" AND created_at >= ?");
+    }
+    if to.is_some() {
+        sql.push_str(" AND created_at < ?");
+    }
+    sql.push_str(" ORDER BY created_at ASC, id ASC");
+    let mut query = sqlx::query_as::<Sqlite, Post>(&sql);
+    if let Some(tag) = tag {
+        query = query.bind(tag);
+    }
+    for t in [from, to].into_iter().flatten() {
+        query = query.bind(t);
+    }
+    Ok(query.fetch_all(super::get_sqlite()).await?)
+}
+
+// 后台博客列表，status 是 pinned、featured 或 normal；to 不包含在内
+pub(crate) async fn list_for_management(
+    tag: Option<&str>,
+    from: Option<i64>,
+    to: Option<i64>,
+    status: Option<&str>,
+) -> Result<Vec<PostDetail>> {
+    let mut sql = format!("SELECT {} FROM posts p WHERE 1 = 1", LIST_COLUMNS);
+    if tag.is_some() {
+        sql.push_str(
+            " AND p.id IN (SELECT u.post_id FROM tags_usage u INNER JOIN tags t ON t.id = u.tag_id WHERE t.name = ?)",
+        );
+    }
+    if from.is_some() {
+        sql.push_str(" AND p.created_at >= ?");
+    }
+    if to.is_some() {
+        sql.push_str(" AND p.created_at < ?");
+    }
+    sql.push_str(match status {
+        Some("pinned") => " AND p.pinned = 1",
+        Some("featured") => " AND p.featured = 1",
+        Some("normal") => " AND p.pinned = 0 AND p.featured = 0",
+        _ => "",
+    });
+    sql.push_str(" ORDER BY p.created_at DESC, p.id DESC");
+    let mut query = sqlx::query_as::<Sqlite, Post>(&sql);
+    if let Some(tag) = tag {
+        query = query.bind(tag);
+    }
+    for t in [from, to].into_iter().flatten() {
+        query = query.bind(t);
+    }
+    to_detail_list(query.fetch_all(super::get_sqlite()).await?).await
+}
+
+pub(crate) async fn list_by_ids(ids: &[i64]) -> Result<Vec<Post>> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let sql = format!(
+        "SELECT * FROM posts WHERE id IN ({}) ORDER BY id DESC",
+        vec!["?"; ids.len()].join(",")
+    );
+    let mut query = sqlx::query_as::<Sqlite, Post>(&sql);
+    for id in ids {
+        query = query.bind(id);
+    }
+    Ok(query.fetch_all(super::get_sqlite()).await?)
+}
+
+pub(crate) const MAX_BULK_POSTS: usize = 500;
+
+async fn bulk_tag_id(transaction: &mut Transaction<'_, Sqlite>, name: &str, create: bool) -> Result<i64> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::BadRequest.into());
+    }
+    if create {
+        sqlx::query("INSERT OR IGNORE INTO tags(name, created_at)VALUES(?,?)")
+            .bind(name)
+            .bind(time::unix_epoch_sec() as i64)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    let row = sqlx::query("SELECT id FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_optional(&mut *transaction)
+        .await?;
+    match row {
+        Some(r) => Ok(r.get(0)),
+        None => Err(Error::TagNotFound.into()),
+    }
+}
+
+// 返回给这篇博客的提示，没有就是空字符串
+async fn bulk_item(
+    transaction: &mut Transaction<'_, Sqlite>,
+    id: i64,
+    action: &BulkAction,
+    tag_id: Option<i64>,
+) -> Result<&'static str> {
+    if let BulkAction::Delete = action {
+        delete_post(transaction, id).await?;
+        return Ok("");
+    }
+    if sqlx::query("SELECT 1 FROM posts WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .is_none()
+    {
+        return Err(Error::CannotFoundPost.into());
+    }
+    let message = match action {
+        BulkAction::AddTag { .. } => {
+            let r = sqlx::query("INSERT OR IGNORE INTO tags_usage(post_id, tag_id)VALUES(?,?)")
+                .bind(id)
+                .bind(tag_id)
+                .execute(&mut *transaction)
+                .await?;
+            if r.rows_affected() == 0 {
+                "已有这个标签/Already tagged"
+            } else {
+                ""
+            }
+        },
+        BulkAction::RemoveTag { .. } => {
+            let r = sqlx::query("DELETE FROM tags_usage WHERE post_id = ? AND tag_id = ?")
+                .bind(id)
+                .bind(tag_id)
+                .execute(&mut *transaction)
+                .await?;
+            if r.rows_affected() == 0 {
+                "没有这个标签/Not tagged"
+            } else {
+                ""
+            }
+        },
+        BulkAction::SetStatus { pinned, featured } => {
+            sqlx::query("UPDATE posts SET pinned = IFNULL(?, pinned), featured = IFNULL(?, featured) WHERE id = ?")
+                .bind(*pinned)
+                .bind(*featured)
+                .bind(id)
+                .execute(&mut *transaction)
+                .await?;
+            ""
+        },
+        // 导出不改数据，只检查博客是否存在
+        BulkAction::Delete | BulkAction::Export => "",
+    };
+    Ok(message)
+}
+
+/// 整批在一个事务里执行；每篇博客用一个保存点，单篇失败只回滚这一篇
+pub(crate) async fn bulk(ids: &[i64], action: &BulkAction) -> Result<Vec<BulkItemResult>> {
+    if ids.is_empty() || ids.len() > MAX_BULK_POSTS {
+        return Err(Error::BadRequest.into());
+    }
+    let mut transaction = super::get_sqlite().begin().await?;
+    let tag_id = match action {
+        BulkAction::AddTag { tag } => Some(bulk_tag_id(&mut transaction, tag, true).await?),
+        BulkAction::RemoveTag { tag } => Some(bulk_tag_id(&mut transaction, tag, false).await?),
+        _ => None,
+    };
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        let mut savepoint = transaction.begin().await?;
+        let (success, message) = match bulk_item(&mut savepoint, *id, action, tag_id).await {
+            Ok(message) => {
+                savepoint.commit().await?;
+                (true, String::from(message))
+            },
+            Err(e) => {
+                savepoint.rollback().await?;
+                (false, e.0.to_string())
+            },
+        };
+        results.push(BulkItemResult {
+            id: *id,
+            success,
+            message,
+        });
+    }
+    if matches!(action, BulkAction::RemoveTag { .. } | BulkAction::Delete) {
+        sqlx::query(tag::REMOVE_ORPHANS_SQL).execute(&mut transaction).await?;
+    }
+    transaction.commit().await?;
+    Ok(results)
+}
+
 pub async fn all_by_since(timestamp: i64) -> Result<Vec<Post>> {
     let posts = sqlx::query_as::<Sqlite, Post>("SELECT * FROM posts WHERE updated_at>=? ORDER BY id DESC")
         .bind(timestamp)
@@ -0,0 +1,225 @@
+use blog_common::dto::{post::PostDetail, PaginationData};
+use sqlx::{Row, Sqlite, SqliteConnection};
+
+use crate::{
+    db::{model::Post, tag},
+    util::result::Result,
+};
+
+// FTS5 自带的 unicode61 分词器会把一整段中文当成一个词，
+// 所以索引前在每个中日韩字符两边加上空格，按单字建索引，查询时再按短语匹配
+const MARK_START: char = '\u{2}';
+const MARK_END: char = '\u{3}';
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{2E80}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+fn split_cjk(text: &str) -> String {
+    let mut s = String::with_capacity(text.len() * 2);
+    for c in text.chars() {
+        if is_cjk(c) {
+            s.push(' ');
+            s.push(c);
+            s.push(' ');
+        } else {
+            s.push(c);
+        }
+    }
+    s
+}
+
+fn to_match_query(keyword: &str) -> String {
+    keyword
+        .split_whitespace()
+        .map(|term| split_cjk(term).split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// 去掉索引时加进去的空格，转义后再把高亮标记换成 <mark>
+// 两边都是中日韩字符（或者在开头结尾）的空格是 split_cjk 加的，直接去掉；
+// 其它位置连续的空格只留一个，这样 "Rust 语言" 不会变成 "Rust语言"
+fn render_snippet(snippet: &str) -> String {
+    let chars: Vec<char> = snippet.chars().collect();
+    let visible = |mut idx: usize, forward: bool| -> Option<char> {
+        loop {
+            if forward {
+                idx += 1;
+                if idx >= chars.len() {
+                    return None;
+                }
+            } else {
+                if idx == 0 {
+                    return None;
+                }
+                idx -= 1;
+            }
+            let c = chars[idx];
+            if c != MARK_START && c != MARK_END {
+                return Some(c);
+            }
+        }
+    };
+    let mut s = String::with_capacity(snippet.len());
+    for (idx, &c) in chars.iter().enumerate() {
+        if c == ' ' {
+            let previous = visible(idx, false);
+            if previous == Some(' ') {
+                continue;
+            }
+            // 跳过这一串空格，看后面第一个字符
+            let mut next = idx;
+            let following = loop {
+                match visible(next, true) {
+                    Some(' ') => next += 1,
+                    c => break c,
+                }
+            };
+            let inserted = previous.map_or(true, is_cjk) && following.map_or(true, is_cjk);
+            if inserted {
+                continue;
+            }
+        }
+        s.push(c);
+    }
+    let s = s.replace(&format!("{}{}", MARK_END, MARK_START), "");
+    v_htmlescape::escape(&s)
+        .to_string()
+        .replace(MARK_START, "<mark>")
+        .replace(MARK_END, "</mark>")
+}
+
+pub(crate) async fn index_post(
+    connection: &mut SqliteConnection,
+    id: i64,
+    title: &str,
+    markdown_content: &str,
+) -> Result<()> {
+    sqlx::query("DELETE FROM posts_fts WHERE rowid = ?")
+        .bind(id)
+        .execute(&mut *connection)
+        .await?;
+    sqlx::query("INSERT INTO posts_fts(rowid, title, content)VALUES(?,?,?)")
+        .bind(id)
+        .bind(split_cjk(title))
+        .bind(split_cjk(markdown_content))
+        .execute(&mut *connection)
+        .await?;
+    Ok(())
+}
+
+pub async fn search(keyword: &str, page: u32, page_size: u8) -> Result<PaginationData<Vec<PostDetail>>> {
+    let match_query = to_match_query(keyword);
+    if match_query.is_empty() {
+        return Ok(PaginationData { total: 0, data: vec![] });
+    }
+    let row = sqlx::query("SELECT COUNT(*) FROM posts_fts WHERE posts_fts MATCH ?")
+        .bind(&match_query)
+        .fetch_one(super::get_sqlite())
+        .await?;
+    let total: i64 = row.get(0);
+    if total < 1 {
+        return Ok(PaginationData { total: 0, data: vec![] });
+    }
+
+    let page = if page < 1 { 1 } else { page };
+    let offset = (page as i64 - 1) * page_size as i64;
+    let posts = sqlx::query_as::<Sqlite, Post>(
+        "SELECT p.id,p.title,p.title_image,'' AS markdown_content,snippet(posts_fts, 1, ?, ?, '...', 32) AS rendered_content,p.created_at,p.updated_at,p.slug FROM posts_fts INNER JOIN posts p ON p.id = posts_fts.rowid WHERE posts_fts MATCH ? ORDER BY posts_fts.rank LIMIT ? OFFSET ?",
+    )
+    .bind(MARK_START.to_string())
+    .bind(MARK_END.to_string())
+    .bind(&match_query)
+    .bind(page_size)
+    .bind(offset)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    if posts.is_empty() {
+        return Ok(PaginationData {
+            total: total as u64,
+            data: vec![],
+        });
+    }
+
+    let post_ids: Vec<i64> = posts.iter().map(|p| p.id).collect();
+    let comment_counts = super::comment::count_by_post_ids(&post_ids).await?;
+    let tags_map = tag::get_tags_by_post_ids(post_ids).await?;
+    let data = posts
+        .iter()
+        .map(|p| {
+            let mut detail: PostDetail = p.into();
+            detail.content = render_snippet(&p.rendered_content);
+            detail.comment_count = comment_counts.get(&p.id).copied().unwrap_or(0);
+            detail.tags = tags_map
+                .get(&p.id)
+                .map(|tags| tags.iter().map(|t| t.name.clone()).collect());
+            detail
+        })
+        .collect();
+    Ok(PaginationData {
+        total: total as u64,
+        data,
+    })
+}
+
+// 老数据库升级后全文索引是空的，启动时按现有博客重建一次
+pub(crate) async fn rebuild_index_if_empty() -> Result<()> {
+    let row = sqlx::query("SELECT COUNT(*) FROM posts_fts")
+        .fetch_one(super::get_sqlite())
+        .await?;
+    let indexed: i64 = row.get(0);
+    if indexed > 0 {
+        return Ok(());
+    }
+    let rows = sqlx::query("SELECT id,title,markdown_content FROM posts")
+        .fetch_all(super::get_sqlite())
+        .await?;
+    let mut transaction = super::get_sqlite().begin().await?;
+    for row in rows.iter() {
+        let id: i64 = row.get(0);
+        let title: String = row.get(1);
+        let markdown_content: String = row.get(2);
+        index_post(&mut transaction, id, &title, &markdown_content).await?;
+    }
+    transaction.commit().await?;
+    if !rows.is_empty() {
+        println!("Rebuilt search index for {} post(s)", rows.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_mixed_cjk_and_ascii() {
+        assert_eq!(split_cjk("Rust语言"), "Rust 语  言 ");
+        assert_eq!(split_cjk("hello world"), "hello world");
+    }
+
+    #[test]
+    fn quote_match_query_terms() {
+        assert_eq!(to_match_query("Rust 语言"), "\"Rust\" \"语 言\"");
+        assert_eq!(to_match_query("a OR b NEAR(c"), "\"a\" \"OR\" \"b\" \"NEAR(c\"");
+        assert_eq!(to_match_query("say\"hi\" *"), "\"say\"\"hi\"\"\" \"*\"");
+        assert_eq!(to_match_query("   "), "");
+    }
+
+    #[test]
+    fn render_snippet_merges_marks_and_keeps_real_spaces() {
+        // "Rust 语言" 建索引后是 "Rust  语  言 "，高亮了 "语" 和 "言"
+        let snippet = format!("Rust  {0}语{1}  {0}言{1} ", MARK_START, MARK_END);
+        assert_eq!(render_snippet(&snippet), "Rust <mark>语言</mark>");
+        let snippet = format!("{0}a{1} < b & c", MARK_START, MARK_END);
+        assert_eq!(render_snippet(&snippet), "<mark>a</mark> &lt; b &amp; c");
+    }
+}
@@ -0,0 +1,165 @@
+use blog_common::{
+    dto::series::{PostSeries, SeriesData, SeriesDetail, SeriesPost},
+    result::Error,
+    util::time,
+};
+use sqlx::{Row, SqliteConnection};
+
+use crate::util::{result::Result, snowflake};
+
+async fn find_id(connection: &mut SqliteConnection, name: &str) -> Result<Option<i64>> {
+    let r = sqlx::query("SELECT id FROM series WHERE name = ?")
+        .bind(name)
+        .fetch_optional(&mut *connection)
+        .await?;
+    Ok(r.map(|row| row.get(0)))
+}
+
+async fn get_or_create(connection: &mut SqliteConnection, name: &str) -> Result<i64> {
+    if let Some(id) = find_id(&mut *connection, name).await? {
+        return Ok(id);
+    }
+    let id = snowflake::gen_id() as i64;
+    sqlx::query("INSERT INTO series(id, name, description, created_at)VALUES(?,?,'',?)")
+        .bind(id)
+        .bind(name)
+        .bind(time::unix_epoch_sec() as i64)
+        .execute(&mut *connection)
+        .await?;
+    Ok(id)
+}
+
+async fn posts_of(series_id: i64) -> Result<Vec<SeriesPost>> {
+    let rows = sqlx::query(
+        "SELECT p.id,p.title,p.slug,s.position FROM series_posts s INNER JOIN posts p ON p.id = s.post_id WHERE s.series_id = ? ORDER BY s.position ASC, p.created_at ASC",
+    )
+    .bind(series_id)
+    .fetch_all(super::get_sqlite())
+    .await?;
+    let posts = rows
+        .iter()
+        .map(|r| SeriesPost {
+            id: r.get(0),
+            title: r.get(1),
+            slug: r.get(2),
+            position: r.get::<i64, usize>(3) as u32,
+        })
+        .collect();
+    Ok(posts)
+}
+
+pub async fn list() -> Result<Vec<String>> {
+    let rows = sqlx::query("SELECT name FROM series ORDER BY created_at DESC")
+        .fetch_all(super::get_sqlite())
+        .await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+}
+
+pub async fn detail(name: &str) -> Result<SeriesDetail> {
+    let row = sqlx::query("SELECT id,name,description FROM series WHERE name = ?")
+        .bind(name)
+        .fetch_optional(super::get_sqlite())
+        .await?;
+    if row.is_none() {
+        return Err(Error::SeriesNotFound.into());
+    }
+    let row = row.unwrap();
+    let id: i64 = row.get(0);
+    Ok(SeriesDetail {
+        id,
+        name: row.get(1),
+        description: row.get(2),
+        posts: posts_of(id).await?,
+    })
+}
+
+pub async fn save(data: SeriesData) -> Result<()> {
+    let name = data.name.trim();
+    if name.is_empty() {
+        return Err(Error::BadRequest.into());
+    }
+    let mut connection = super::get_sqlite().acquire().await?;
+    let id = get_or_create(&mut connection, name).await?;
+    sqlx::query("UPDATE series SET description=? WHERE id=?")
+        .bind(data.description.trim())
+        .bind(id)
+        .execute(&mut connection)
+        .await?;
+    Ok(())
+}
+
+// 没有指定位置时，已经在这个系列里的保持原位，否则排到最后
+pub(super) async fn assign(
+    connection: &mut SqliteConnection,
+    post_id: i64,
+    name: Option<&str>,
+    position: Option<u32>,
+) -> Result<()> {
+    let name = name.map(|n| n.trim()).unwrap_or_default();
+    if name.is_empty() {
+        return remove_post(connection, post_id).await;
+    }
+    let series_id = get_or_create(&mut *connection, name).await?;
+    let position = match position {
+        Some(p) => p as i64,
+        None => {
+            let current = sqlx::query("SELECT position FROM series_posts WHERE post_id = ? AND series_id = ?")
+                .bind(post_id)
+                .bind(series_id)
+                .fetch_optional(&mut *connection)
+                .await?;
+            match current {
+                Some(row) => row.get(0),
+                None => {
+                    let row = sqlx::query("SELECT IFNULL(MAX(position), 0) FROM series_posts WHERE series_id = ?")
+                        .bind(series_id)
+                        .fetch_one(&mut *connection)
+                        .await?;
+                    row.get::<i64, usize>(0) + 1
+                },
+            }
+        },
+    };
+    sqlx::query("REPLACE INTO series_posts(post_id, series_id, position)VALUES(?,?,?)")
+        .bind(post_id)
+        .bind(series_id)
+        .bind(position)
+        .execute(&mut *connection)
+        .await?;
+    Ok(())
+}
+
+async fn remove_post(connection: &mut SqliteConnection, post_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM series_posts WHERE post_id = ?")
+        .bind(post_id)
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn of_post(post_id: i64) -> Result<Option<PostSeries>> {
+    let row = sqlx::query(
+        "SELECT s.id,s.name,s.description,u.position FROM series s INNER JOIN series_posts u ON s.id = u.series_id WHERE u.post_id = ?",
+    )
+    .bind(post_id)
+    .fetch_optional(super::get_sqlite())
+    .await?;
+    if row.is_none() {
+        return Ok(None);
+    }
+    let row = row.unwrap();
+    let posts = posts_of(row.get(0)).await?;
+    let idx = match posts.iter().position(|p| p.id == post_id) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+    Ok(Some(PostSeries {
+        name: row.get(1),
+        description: row.get(2),
+        position: row.get::<i64, usize>(3) as u32,
+        index: idx as u32 + 1,
+        total: posts.len() as u32,
+        prev: if idx > 0 { posts.get(idx - 1).cloned() } else { None },
+        next: posts.get(idx + 1).cloned(),
+    }))
+}
@@ -9,10 +9,14 @@ use std::{
 };
 
 use ahash::AHasher;
-use blog_common::{dto::tag::TagUsageAmount, result::Error, util::time};
+use blog_common::{
+    dto::tag::{TagData, TagDetail, TagUsageAmount},
+    result::Error,
+    util::time,
+};
 use bytes::{Buf, Bytes, BytesMut};
 use parking_lot::RwLock;
-use sqlx::{Row, Sqlite};
+use sqlx::{Row, Sqlite, SqliteConnection};
 use tokio::{
     fs::{remove_file, rename, File, OpenOptions},
     io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter},
@@ -65,7 +69,14 @@ pub async fn get_names(id_array: Vec<i64>) -> Result<Vec<String>> {
     Ok(name_list)
 }
 
-pub(super) async fn record_usage(post_id: i64, tags: &Vec<String>) -> Result<()> {
+pub(super) async fn record_usage(connection: &mut SqliteConnection, post_id: i64, tags: &[String]) -> Result<()> {
+    if tags.is_empty() {
+        sqlx::query("DELETE FROM tags_usage WHERE post_id = ?")
+            .bind(post_id)
+            .execute(&mut *connection)
+            .await?;
+        return delete_orphans(connection).await;
+    }
     // query id list by name list
     let mut sql = String::with_capacity(256);
     sql.push_str("SELECT id,name from tags WHERE name IN (");
@@ -78,28 +89,30 @@ pub(super) async fn record_usage(post_id: i64, tags: &Vec<String>) -> Result<()>
     for tag in tags.iter() {
         query = query.bind(tag);
     }
-    let mut tags_in_db = query.fetch_all(&DATA_SOURCE.get().unwrap().sqlite).await?;
+    let mut tags_in_db = query.fetch_all(&mut *connection).await?;
 
     // 查看有没有新的tag
     if tags_in_db.len() < tags.len() {
         let mut new_tags: Vec<Tag> = Vec::with_capacity(tags.len() - tags_in_db.len());
-        {
-            let mut tags_in_db_iter = tags_in_db.iter();
-            for tag in tags.iter() {
-                if !tags_in_db_iter.any(|e| e.name.eq(tag)) {
-                    let id = sqlx::query("REPLACE INTO tags(name, created_at)VALUES(?,?)")
-                        .bind(tag)
-                        .bind(time::unix_epoch_sec() as i64)
-                        .execute(&DATA_SOURCE.get().unwrap().sqlite)
-                        .await?
-                        .last_insert_rowid();
-                    let new_tag = Tag {
-                        id,
-                        name: String::from(tag),
-                    };
-                    new_tags.push(new_tag);
-                }
+        for tag in tags.iter() {
+            if tags_in_db.iter().chain(new_tags.iter()).any(|e| e.name.eq(tag)) {
+                continue;
             }
+            // 不能用 REPLACE，已有的标签会被删掉重建，id、描述和颜色都会丢
+            sqlx::query("INSERT OR IGNORE INTO tags(name, created_at)VALUES(?,?)")
+                .bind(tag)
+                .bind(time::unix_epoch_sec() as i64)
+                .execute(&mut *connection)
+                .await?;
+            let id: i64 = sqlx::query("SELECT id FROM tags WHERE name = ?")
+                .bind(tag)
+                .fetch_one(&mut *connection)
+                .await?
+                .get(0);
+            new_tags.push(Tag {
+                id,
+                name: String::from(tag),
+            });
         }
         tags_in_db.append(&mut new_tags);
     }
@@ -117,17 +130,144 @@ pub(super) async fn record_usage(post_id: i64, tags: &Vec<String>) -> Result<()>
         for tag in tags_in_db.iter() {
             query = query.bind(tag.id);
         }
-        let _tags_in_db = query.execute(&DATA_SOURCE.get().unwrap().sqlite).await?;
+        let _tags_in_db = query.execute(&mut *connection).await?;
     }
 
-    let post_id = post_id;
     for tag in tags_in_db {
-        sqlx::query("REPLACE INTO tags_usage(post_id, tag_id)VALUES(?,?)")
+        sqlx::query("INSERT OR IGNORE INTO tags_usage(post_id, tag_id)VALUES(?,?)")
             .bind(post_id)
             .bind(tag.id)
-            .execute(&DATA_SOURCE.get().unwrap().sqlite)
+            .execute(&mut *connection)
             .await?;
     }
+    delete_orphans(connection).await
+}
+
+// 没有任何博客使用的标签直接删掉
+pub(super) const REMOVE_ORPHANS_SQL: &str =
+    "DELETE FROM tags WHERE NOT EXISTS (SELECT 1 FROM tags_usage u WHERE u.tag_id = tags.id)";
+
+async fn delete_orphans(connection: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(REMOVE_ORPHANS_SQL).execute(connection).await?;
+    Ok(())
+}
+
+pub async fn list_detail() -> Result<Vec<TagDetail>> {
+    let tags = sqlx::query("SELECT t.id,t.name,t.description,t.colour,IFNULL(u.amount, 0) FROM tags t LEFT JOIN (SELECT tag_id, COUNT(tag_id) AS amount FROM tags_usage GROUP BY tag_id) u ON t.id=u.tag_id ORDER BY t.name ASC")
+        .fetch_all(&DATA_SOURCE.get().unwrap().sqlite)
+        .await?;
+    let list = tags
+        .iter()
+        .map(|i| TagDetail {
+            id: i.get(0),
+            name: i.get(1),
+            description: i.get(2),
+            colour: i.get(3),
+            amount: i.get(4),
+        })
+        .collect::<Vec<TagDetail>>();
+    Ok(list)
+}
+
+// 给编辑器的标签输入框做前缀补全
+pub async fn suggest(prefix: &str) -> Result<Vec<String>> {
+    let prefix = prefix.trim();
+    if prefix.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut pattern = String::with_capacity(prefix.len() + 4);
+    for c in prefix.chars() {
+        if c == '%' || c == '_' || c == '\\' {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    let tag_list = sqlx::query_as::<Sqlite, Tag>(
+        "SELECT id,name FROM tags WHERE name LIKE ? ESCAPE '\\' ORDER BY name ASC LIMIT 10",
+    )
+    .bind(pattern)
+    .fetch_all(&DATA_SOURCE.get().unwrap().sqlite)
+    .await?;
+    Ok(tag_list.iter().map(|i| i.name.clone()).collect())
+}
+
+fn is_valid_colour(colour: &str) -> bool {
+    colour.is_empty()
+        || (colour.len() == 7 && colour.starts_with('#') && colour[1..].chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+pub async fn update(data: TagData) -> Result<()> {
+    let name = data.name.trim();
+    let colour = data.colour.trim();
+    if name.is_empty() || !is_valid_colour(colour) {
+        return Err(Error::BadRequest.into());
+    }
+    let r = sqlx::query("SELECT id FROM tags WHERE name = ? AND id <> ?")
+        .bind(name)
+        .bind(data.id)
+        .fetch_optional(&DATA_SOURCE.get().unwrap().sqlite)
+        .await?;
+    if r.is_some() {
+        return Err(Error::TagAlreadyExists.into());
+    }
+    let r = sqlx::query("UPDATE tags SET name=?, description=?, colour=?, updated_at=? WHERE id=?")
+        .bind(name)
+        .bind(data.description.trim())
+        .bind(colour)
+        .bind(time::unix_epoch_sec() as i64)
+        .bind(data.id)
+        .execute(&DATA_SOURCE.get().unwrap().sqlite)
+        .await?;
+    if r.rows_affected() < 1 {
+        return Err(Error::TagNotFound.into());
+    }
+    Ok(())
+}
+
+// 把 from 标签的博客都改成 into 标签，然后删掉 from
+pub async fn merge(from_id: i64, into_id: i64) -> Result<()> {
+    if from_id == into_id {
+        return Err(Error::BadRequest.into());
+    }
+    let r = sqlx::query("SELECT COUNT(*) FROM tags WHERE id IN (?,?)")
+        .bind(from_id)
+        .bind(into_id)
+        .fetch_one(&DATA_SOURCE.get().unwrap().sqlite)
+        .await?;
+    if r.get::<i64, usize>(0) < 2 {
+        return Err(Error::TagNotFound.into());
+    }
+    let mut transaction = DATA_SOURCE.get().unwrap().sqlite.begin().await?;
+    // 两个标签都用到的博客，UPDATE 会违反唯一约束，这些行忽略后在下面删除
+    sqlx::query("UPDATE OR IGNORE tags_usage SET tag_id=? WHERE tag_id=?")
+        .bind(into_id)
+        .bind(from_id)
+        .execute(&mut transaction)
+        .await?;
+    sqlx::query("DELETE FROM tags_usage WHERE tag_id=?")
+        .bind(from_id)
+        .execute(&mut transaction)
+        .await?;
+    sqlx::query("DELETE FROM tags WHERE id=?")
+        .bind(from_id)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+pub async fn delete(id: i64) -> Result<()> {
+    let mut transaction = DATA_SOURCE.get().unwrap().sqlite.begin().await?;
+    sqlx::query("DELETE FROM tags_usage WHERE tag_id=?")
+        .bind(id)
+        .execute(&mut transaction)
+        .await?;
+    sqlx::query("DELETE FROM tags WHERE id=?")
+        .bind(id)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
     Ok(())
 }
 
@@ -156,3 +296,59 @@ pub(crate) async fn get_tags_by_post_ids(ids: Vec<i64>) -> Result<HashMap<i64, V
 
     Ok(d)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use sqlx::{sqlite::SqlitePoolOptions, Row, SqlitePool};
+
+    async fn memory_pool() -> SqlitePool {
+        // 内存库每个连接都是独立的，只能用一个连接
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::migration::migrate(&pool, Path::new(":memory:"), true)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn existing_tag_keeps_id_and_colour() {
+        let pool = memory_pool().await;
+        let mut connection = pool.acquire().await.unwrap();
+        super::record_usage(&mut connection, 1, &["rust".to_string()])
+            .await
+            .unwrap();
+        sqlx::query("UPDATE tags SET colour = '#f00' WHERE name = 'rust'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        let id: i64 = sqlx::query("SELECT id FROM tags WHERE name = 'rust'")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .get(0);
+
+        // 新标签排在已有标签前面
+        super::record_usage(&mut connection, 2, &["wasm".to_string(), "rust".to_string()])
+            .await
+            .unwrap();
+        let row = sqlx::query("SELECT id, colour FROM tags WHERE name = 'rust'")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<i64, _>(0), id);
+        assert_eq!(row.get::<String, _>(1), "#f00");
+        let usage: i64 = sqlx::query("SELECT COUNT(*) FROM tags_usage WHERE tag_id = ?")
+            .bind(id)
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(usage, 2);
+    }
+}
@@ -3,16 +3,33 @@ use core::result::Result;
 use hyper::{body::Body, header};
 use warp::{filters::path::Tail, http::Response, Rejection, Reply};
 
-use crate::service::asset;
+use crate::service::{asset, highlight};
+
+// 代码高亮的主题可以在设置里切换，所以 CSS 是动态生成的
+const HIGHLIGHT_CSS: &'static str = "highlight.css";
 
 pub async fn index() -> Result<impl Reply, Rejection> {
     Ok(response_asset("index.html"))
 }
 
 pub async fn get_asset(tail: Tail) -> Result<Response<Body>, Rejection> {
+    if tail.as_str().eq(HIGHLIGHT_CSS) {
+        return Ok(response_highlight_css().await);
+    }
     Ok(response_asset(tail.as_str()))
 }
 
+async fn response_highlight_css() -> Response<Body> {
+    let theme = highlight::current_theme().await.unwrap_or_else(|e| {
+        eprintln!("{:?}", e.0);
+        String::from(highlight::DEFAULT_THEME)
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/css; charset=utf-8")
+        .body(highlight::theme_css(&theme).into())
+        .unwrap()
+}
+
 fn response_asset(asset: &str) -> Response<Body> {
     let file = asset::get_asset(asset);
     if file.is_none() {
@@ -0,0 +1,61 @@
+use core::result::Result;
+
+use blog_common::{
+    dto::{
+        comment::{CommentData, CommentSubmitted},
+        user::UserInfo,
+    },
+    result::Error,
+};
+use warp::{Rejection, Reply};
+
+use crate::{
+    db::comment,
+    facade::{wrap_json_data, wrap_json_err},
+    service::status,
+};
+
+pub async fn list(post_id: i64) -> Result<impl Reply, Rejection> {
+    match comment::list(post_id).await {
+        Ok(list) => Ok(wrap_json_data(&list)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+// 登录的管理员直接通过，匿名评论需要验证码并进入审核队列
+pub async fn add(token: Option<String>, data: CommentData) -> Result<impl Reply, Rejection> {
+    let approved = status::check_auth(token.clone()).is_ok();
+    if !approved {
+        if let Err(e) = status::require_verify_code(token, &data.captcha) {
+            return Ok(wrap_json_err(400, e.0));
+        }
+    }
+    match comment::add(data, approved).await {
+        Ok((status, comment)) => Ok(wrap_json_data(&CommentSubmitted { status, comment })),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn moderate(id: i64, status: String, user: Option<UserInfo>) -> Result<impl Reply, Rejection> {
+    if user.is_none() {
+        return Ok(wrap_json_err(403, Error::NotAuthed));
+    }
+    let status = match comment::parse_status(&status) {
+        Some(s) => s,
+        None => return Ok(wrap_json_err(400, Error::BadRequest)),
+    };
+    match comment::moderate(id, status).await {
+        Ok(_) => Ok(wrap_json_data("Updated")),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn delete(id: i64, user: Option<UserInfo>) -> Result<impl Reply, Rejection> {
+    if user.is_none() {
+        return Ok(wrap_json_err(403, Error::NotAuthed));
+    }
+    match comment::delete(id).await {
+        Ok(_) => Ok(wrap_json_data("Deleted")),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
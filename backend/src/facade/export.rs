@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use blog_common::{
     dto::{git::GitRepositoryInfo, user::UserInfo, Response as ApiResponse},
     result::{Error, ErrorResponse},
@@ -11,11 +13,18 @@ use crate::{
     db::management,
     db::post,
     facade::{session_id_cookie, wrap_json_data, wrap_json_err},
-    service::{export, status},
+    service::{
+        epub::{self, EpubOptions},
+        export, import, status,
+    },
     util::common,
 };
 
-pub async fn export_handler(tail: Tail, user: Option<UserInfo>) -> Result<Response<Body>, Rejection> {
+pub async fn export_handler(
+    tail: Tail,
+    query_string: HashMap<String, String>,
+    user: Option<UserInfo>,
+) -> Result<Response<Body>, Rejection> {
     if user.is_none() {
         return Ok(Response::builder().status(403).body("".into()).unwrap());
     }
@@ -23,19 +32,27 @@ pub async fn export_handler(tail: Tail, user: Option<UserInfo>) -> Result<Respon
     if path.eq("hugo") {
         return hugo().await;
     }
-    if path.rfind(".zip").is_some() {
+    if path.eq("epub") {
+        return epub(&query_string).await;
+    }
+    if path.rfind(".zip").is_some() || path.ends_with(".epub") {
         return Ok(get_file(path));
     }
     Ok(Response::builder().status(404).body("".into()).unwrap())
 }
 
 fn get_file(file: &str) -> Response<Body> {
+    let content_type = if file.ends_with(".epub") {
+        "application/epub+zip"
+    } else {
+        "application/octet-stream"
+    };
     let file = std::env::current_dir().unwrap().join("export").join(file);
     if file.exists() {
         match std::fs::read(file.as_path()) {
             Ok(d) => {
                 return Response::builder()
-                    .header(header::CONTENT_TYPE, "application/octet-stream")
+                    .header(header::CONTENT_TYPE, content_type)
                     .header(header::CONTENT_LENGTH, d.len())
                     .body(d.into())
                     .unwrap()
@@ -48,16 +65,60 @@ fn get_file(file: &str) -> Response<Body> {
     Response::builder().status(404).body("".into()).unwrap()
 }
 
-async fn hugo() -> Result<Response<Body>, Rejection> {
-    let filename = export::hugo().await?;
+fn export_uri(filename: &str) -> Response<Body> {
     let mut uri = String::with_capacity(64);
     uri.push_str("/export/");
-    uri.push_str(&filename);
-    // Ok(warp::redirect::temporary(warp::http::Uri::from_static(&uri)))
-    let r = Response::builder()
+    uri.push_str(filename);
+    Response::builder()
         .header(header::CONTENT_TYPE, "text/plain")
         .header(header::CONTENT_LENGTH, uri.len())
         .body(uri.into())
-        .unwrap();
-    Ok(r)
+        .unwrap()
+}
+
+// ?tag= 只导出某个标签，?from= 和 ?to= 是发表日期范围（包含两端），?title= 和 ?lang= 是书名和语言
+// BCP 47 语言标签，例如 zh-CN、en、zh-Hant-TW，会原样写进 XHTML 和 OPF
+fn language_tag(s: &str) -> Option<String> {
+    let s = s.trim();
+    let valid = !s.is_empty()
+        && s.len() <= 35
+        && s.split('-')
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_alphanumeric()));
+    valid.then(|| String::from(s))
+}
+
+async fn epub(query_string: &HashMap<String, String>) -> Result<Response<Body>, Rejection> {
+    let param = |name: &str| query_string.get(name).filter(|v| !v.is_empty());
+    let mut dates = [None, None];
+    for (i, name) in ["from", "to"].iter().enumerate() {
+        if let Some(d) = param(name) {
+            match import::parse_date(d) {
+                Some(t) => dates[i] = Some(t),
+                None => return Ok(wrap_json_err(400, Error::BadRequest).into_response()),
+            }
+        }
+    }
+    let tag = param("tag").cloned();
+    let options = EpubOptions {
+        title: param("title")
+            .cloned()
+            .unwrap_or_else(|| tag.clone().unwrap_or_else(|| String::from("Blog"))),
+        language: param("lang")
+            .and_then(|l| language_tag(l))
+            .unwrap_or_else(|| String::from("zh-CN")),
+        tag,
+        from: dates[0],
+        // 结束日期当天的也包含在内
+        to: dates[1].map(|t| t + 86400),
+    };
+    match epub::export(&options).await {
+        Ok(filename) => Ok(export_uri(&filename)),
+        Err(e) => Ok(wrap_json_err(400, e.0).into_response()),
+    }
+}
+
+async fn hugo() -> Result<Response<Body>, Rejection> {
+    let filename = export::hugo().await?;
+    // Ok(warp::redirect::temporary(warp::http::Uri::from_static(&uri)))
+    Ok(export_uri(&filename))
 }
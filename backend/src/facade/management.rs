@@ -1,13 +1,16 @@
 use core::{convert::Infallible, result::Result};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 use blog_common::{
     dto::{
+        comment::CommentStatus,
         management::{AdminUser, Setting},
+        post::{BulkAction, BulkReport, BulkRequest},
         user::UserInfo,
     },
     result::Error,
 };
+use bytes::Bytes;
 use hyper::{body::Body, header};
 use warp::{http::Uri, reply::Response, Rejection, Reply};
 
@@ -15,13 +18,32 @@ use crate::{
     db::management,
     facade,
     facade::{wrap_json_data, wrap_json_err},
-    service::status,
-    util::common,
+    service::{
+        backup, export, highlight, image,
+        import::{self, SourceFiles},
+        render,
+        render::MarkdownExtensions,
+        status,
+    },
+    util::{common, val},
 };
 
 pub const SETTINGS_HTML: &'static str = include_str!("../resource/page/settings.html");
 const LOGIN_HTML: &'static str = include_str!("../resource/page/login.html");
+const IMPORT_HTML: &'static str = include_str!("../resource/page/import.html");
 const POST_DETAIL_DEFAULT_TEMPLATE: &'static str = include_str!("../resource/static-site/template/post_detail.html");
+// 顺序即管理页面上的显示顺序
+const MARKDOWN_EXTENSION_LABELS: [(&'static str, &'static str); 9] = [
+    ("table", "表格/Tables"),
+    ("strikethrough", "删除线/Strikethrough"),
+    ("tasklist", "任务列表/Task lists"),
+    ("footnotes", "脚注/Footnotes"),
+    ("autolink", "自动链接/Autolinks"),
+    ("shortcodes", "Emoji 短代码/Emoji shortcodes"),
+    ("superscript", "上标/Superscript"),
+    ("description_lists", "定义列表/Description lists"),
+    ("math", "数学公式/Math formulas ($...$, $$...$$)"),
+];
 
 pub fn show_settings_with_fake_auth() -> Response {
     let token = common::simple_uuid();
@@ -54,6 +76,22 @@ pub async fn update_settings(token: Option<String>, setting: Setting) -> Result<
     if let Err(e) = status::check_auth(token) {
         return facade::response(Err(e));
     }
+    if setting.item.eq(val::MARKDOWN_EXTENSIONS)
+        && serde_json::from_str::<MarkdownExtensions>(&setting.content).is_err()
+    {
+        return facade::response(Err(Error::BadRequest.into()));
+    }
+    if setting.item.eq(val::HIGHLIGHT_THEME) && !highlight::is_valid_theme(&setting.content) {
+        return facade::response(Err(Error::BadRequest.into()));
+    }
+    if setting.item.eq(val::BACKUP_SCHEDULE)
+        && serde_json::from_str::<backup::BackupSchedule>(&setting.content)
+            .ok()
+            .and_then(|s| s.seconds_of_day())
+            .is_none()
+    {
+        return facade::response(Err(Error::BadRequest.into()));
+    }
     facade::response(management::update_setting(setting.into()).await)
 }
 
@@ -116,3 +154,312 @@ pub async fn update_render_templates(
         Err(e) => facade::response(Err(e)),
     }
 }
+
+pub async fn show_tags_page(token: Option<String>) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/tags").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    let tags = match crate::db::tag::list_detail().await {
+        Ok(t) => t,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let mut context = tera::Context::new();
+    context.insert("tags", &tags);
+    let html = match crate::service::export::TEMPLATES.render("tags.html", &context) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            format!("Failed render page: {}", e)
+        },
+    };
+    Ok(response.body(html.into()).unwrap())
+}
+
+pub async fn show_comments_page(
+    token: Option<String>,
+    query_string: HashMap<String, String>,
+) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/comments").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    let status = query_string.get("status").map_or("pending", |s| s.as_str());
+    let comment_status = crate::db::comment::parse_status(status).unwrap_or(CommentStatus::Pending);
+    let comments = match crate::db::comment::list_by_status(comment_status).await {
+        Ok(c) => c,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let mut context = tera::Context::new();
+    context.insert("status", &comment_status);
+    context.insert("comments", &comments);
+    let html = match crate::service::export::TEMPLATES.render("comments.html", &context) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            format!("Failed render page: {}", e)
+        },
+    };
+    Ok(response.body(html.into()).unwrap())
+}
+
+// ?tag= ?from= ?to= ?status= 和 EPUB 导出一样，日期包含两端
+pub async fn show_posts_page(
+    token: Option<String>,
+    query_string: HashMap<String, String>,
+) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/posts").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    let param = |name: &str| query_string.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let from = param("from").and_then(import::parse_date);
+    let to = param("to").and_then(import::parse_date).map(|t| t + 86400);
+    let posts = match crate::db::post::list_for_management(param("tag"), from, to, param("status")).await {
+        Ok(p) => p,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let tags = match crate::db::tag::list_detail().await {
+        Ok(t) => t,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let mut context = tera::Context::new();
+    context.insert("posts", &posts);
+    context.insert("tags", &tags);
+    for name in ["tag", "from", "to", "status"] {
+        context.insert(name, param(name).unwrap_or_default());
+    }
+    let html = match crate::service::export::TEMPLATES.render("posts.html", &context) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            format!("Failed render page: {}", e)
+        },
+    };
+    Ok(response.body(html.into()).unwrap())
+}
+
+pub async fn bulk_posts(token: Option<String>, request: BulkRequest) -> Result<impl Reply, Rejection> {
+    if let Err(e) = status::check_auth(token) {
+        return facade::response(Err(e));
+    }
+    let results = match crate::db::post::bulk(&request.ids, &request.action).await {
+        Ok(r) => r,
+        Err(e) => return facade::response(Err(e)),
+    };
+    let mut report = BulkReport {
+        results,
+        download_uri: None,
+    };
+    // 数据库里的记录已经提交，图片删不掉只记日志
+    if request.action == BulkAction::Delete {
+        for r in report.results.iter().filter(|r| r.success) {
+            if let Err(e) = image::delete_post_images(r.id as u64).await {
+                eprintln!("{:?}", e);
+            }
+        }
+    }
+    if request.action == BulkAction::Export {
+        let ids: Vec<i64> = report.results.iter().filter(|r| r.success).map(|r| r.id).collect();
+        if !ids.is_empty() {
+            match export::hugo_selection(&ids).await {
+                Ok(filename) => report.download_uri = Some(format!("/export/{}", filename)),
+                Err(e) => return facade::response(Err(e)),
+            }
+        }
+    }
+    facade::response(Ok(report))
+}
+
+const DEFAULT_ANALYTICS_DAYS: u32 = 30;
+const MAX_ANALYTICS_DAYS: u32 = 366;
+
+fn analytics_days(query_string: &HashMap<String, String>) -> u32 {
+    query_string
+        .get("days")
+        .and_then(|d| d.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_ANALYTICS_DAYS)
+        .clamp(1, MAX_ANALYTICS_DAYS)
+}
+
+pub async fn analytics(token: Option<String>, query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    if let Err(e) = status::check_auth(token) {
+        return facade::response(Err(e));
+    }
+    facade::response(crate::db::analytics::dashboard(analytics_days(&query_string)).await)
+}
+
+pub async fn rerender(token: Option<String>, query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    if let Err(e) = status::check_auth(token) {
+        return facade::response(Err(e));
+    }
+    let force = query_string.get("force").map_or(false, |f| f.eq("true"));
+    let report = crate::db::post::rerender(force, |r| {
+        println!("Re-rendered {}/{} post(s)", r.rendered + r.failures.len(), r.total)
+    })
+    .await;
+    facade::response(report)
+}
+
+pub async fn show_import_page(token: Option<String>) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/import").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    Ok(response.body(IMPORT_HTML.into()).unwrap())
+}
+
+// 上传的压缩包，或者 ?dir= 指定的服务器目录
+fn import_source(query_string: &HashMap<String, String>, body: &[u8]) -> crate::util::result::Result<SourceFiles> {
+    match query_string.get("dir") {
+        Some(dir) => import::read_dir(Path::new(dir)),
+        None => import::read_zip(body),
+    }
+}
+
+pub async fn import_markdown(
+    token: Option<String>,
+    query_string: HashMap<String, String>,
+    body: Bytes,
+) -> Result<impl Reply, Rejection> {
+    if let Err(e) = status::check_auth(token) {
+        return facade::response(Err(e));
+    }
+    match import_source(&query_string, &body) {
+        Ok(files) => facade::response(Ok(import::markdown::import(&files).await)),
+        Err(e) => facade::response(Err(e)),
+    }
+}
+
+// 请求体是 WXR 文件，?uploads= 指定服务器上的 wp-content/uploads 目录，?dry_run=true 只预览
+pub async fn import_wordpress(
+    token: Option<String>,
+    query_string: HashMap<String, String>,
+    body: Bytes,
+) -> Result<impl Reply, Rejection> {
+    if let Err(e) = status::check_auth(token) {
+        return facade::response(Err(e));
+    }
+    let xml = match std::str::from_utf8(&body) {
+        Ok(x) => x.trim_start_matches('\u{feff}'),
+        Err(_) => return facade::response(Err(Error::BadRequest.into())),
+    };
+    let uploads = query_string.get("uploads").filter(|u| !u.is_empty()).map(Path::new);
+    let dry_run = query_string.get("dry_run").map_or(false, |d| d.eq("true"));
+    facade::response(import::wordpress::import(xml, uploads, dry_run).await)
+}
+
+pub async fn show_backup_page(token: Option<String>) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/backup").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    let schedule = match backup::load_schedule().await {
+        Ok(s) => s,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let mut context = tera::Context::new();
+    context.insert("schedule", &schedule);
+    context.insert("status", &backup::status());
+    context.insert("backups", &backup::list(Path::new(backup::BACKUP_DIR)));
+    let html = match crate::service::export::TEMPLATES.render("backup.html", &context) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            format!("Failed render page: {}", e)
+        },
+    };
+    Ok(response.body(html.into()).unwrap())
+}
+
+// 生成一份完整备份并下载，同时保留在 backup 目录里
+pub async fn download_backup(token: Option<String>) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/backup").into_response());
+    }
+    let data = match backup::create(Path::new(backup::BACKUP_DIR)).await {
+        Ok(path) => tokio::fs::read(&path)
+            .await
+            .map(|d| (path, d))
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.0.to_string()),
+    };
+    match data {
+        Ok((path, d)) => {
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            let disposition = format!("attachment; filename=\"{}\"", file_name);
+            Ok(warp::http::Response::builder()
+                .header(header::CONTENT_TYPE, "application/zip")
+                .header(header::CONTENT_LENGTH, d.len())
+                .header(header::CONTENT_DISPOSITION, disposition)
+                .body(d.into())
+                .unwrap())
+        },
+        Err(e) => {
+            eprintln!("Backup failed: {}", e);
+            Ok(warp::http::Response::builder()
+                .status(500)
+                .header("Content-Type", "text/plain; charset=utf-8")
+                .body(format!("备份失败/Backup failed: {}", e).into())
+                .unwrap())
+        },
+    }
+}
+
+pub async fn show_markdown_page(token: Option<String>) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/markdown").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    let enabled = match render::load_extensions().await {
+        Ok(e) => serde_json::to_value(e).unwrap(),
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let theme = match highlight::current_theme().await {
+        Ok(t) => t,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let extensions: Vec<serde_json::Value> = MARKDOWN_EXTENSION_LABELS
+        .iter()
+        .map(|(name, label)| serde_json::json!({ "name": name, "label": label, "enabled": enabled[name] }))
+        .collect();
+    let mut context = tera::Context::new();
+    context.insert("extensions", &extensions);
+    context.insert("themes", &highlight::themes());
+    context.insert("current_theme", &theme);
+    let html = match crate::service::export::TEMPLATES.render("markdown.html", &context) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            format!("Failed render page: {}", e)
+        },
+    };
+    Ok(response.body(html.into()).unwrap())
+}
+
+pub async fn show_analytics_page(
+    token: Option<String>,
+    query_string: HashMap<String, String>,
+) -> Result<warp::http::Response<Body>, Rejection> {
+    if status::check_auth(token).is_err() {
+        return Ok(super::management_sign_in("/management/analytics").into_response());
+    }
+    let response = warp::http::Response::builder().header("Content-Type", "text/html; charset=utf-8");
+    let dashboard = match crate::db::analytics::dashboard(analytics_days(&query_string)).await {
+        Ok(d) => d,
+        Err(e) => return Ok(response.body(format!("{:?}", e.0).into()).unwrap()),
+    };
+    let max_daily_views = dashboard.trend.iter().map(|d| d.views).max().unwrap_or(0).max(1);
+    let mut context = tera::Context::new();
+    context.insert("dashboard", &dashboard);
+    context.insert("max_daily_views", &max_daily_views);
+    context.insert("ranges", &[7u32, 30, 90, 365]);
+    let html = match crate::service::export::TEMPLATES.render("analytics.html", &context) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            format!("Failed render page: {}", e)
+        },
+    };
+    Ok(response.body(html.into()).unwrap())
+}
@@ -1,10 +1,12 @@
 pub(crate) mod asset;
+pub(crate) mod comment;
 pub(crate) mod export;
 pub(crate) mod git;
 pub(crate) mod image;
 pub(crate) mod index;
 pub(crate) mod management;
 pub(crate) mod post;
+pub(crate) mod series;
 pub(crate) mod tag;
 pub(crate) mod user;
 
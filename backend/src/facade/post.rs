@@ -19,9 +19,12 @@ use warp::{
 };
 
 use crate::{
-    db::post,
+    db::{post, search},
     facade::{session_id_cookie, wrap_json_data, wrap_json_err},
-    service::{image, status},
+    service::{
+        analytics::{self, Visitor},
+        image, status,
+    },
     util::common,
 };
 
@@ -35,15 +38,92 @@ pub async fn new(token: Option<String>) -> Result<impl Reply, Rejection> {
         .or_else(|e| Ok(wrap_json_err(500, e.0)))
 }
 
-pub async fn list(pagination_type: String, post_id: u64) -> Result<impl Reply, Rejection> {
-    match post::list(pagination_type.as_str(), post_id, val::POSTS_PAGE_SIZE).await {
+// page=页码，before/after=游标（博客 id），size=每页数量，sort=created|updated|title，order=asc|desc
+fn list_options(query_string: &HashMap<String, String>) -> post::ListOptions {
+    let mut options = post::ListOptions::default();
+    if let Some(page) = query_string.get("page").and_then(|p| p.parse::<u32>().ok()) {
+        options.page = page.max(1);
+    }
+    if let Some(id) = query_string.get("after").and_then(|p| p.parse::<i64>().ok()) {
+        options.cursor = Some(post::Cursor::After(id));
+    } else if let Some(id) = query_string.get("before").and_then(|p| p.parse::<i64>().ok()) {
+        options.cursor = Some(post::Cursor::Before(id));
+    }
+    if let Some(size) = query_string.get("size").and_then(|p| p.parse::<u8>().ok()) {
+        options.page_size = size.clamp(1, val::MAX_POSTS_PAGE_SIZE);
+    }
+    if let Some(sort) = query_string.get("sort").and_then(|s| post::SortField::parse(s)) {
+        options.sort = sort;
+    }
+    // 标题默认升序，时间默认倒序
+    options.asc = match query_string.get("order").map(|s| s.as_str()) {
+        Some("asc") => true,
+        Some("desc") => false,
+        _ => options.sort == post::SortField::Title,
+    };
+    // 浏览器的语言，例如 zh-CN，只取主语言
+    options.language = query_string
+        .get("lang")
+        .and_then(|l| l.split(|c| c == '-' || c == '_').next())
+        .map(|l| l.trim().to_ascii_lowercase())
+        .filter(|l| !l.is_empty() && l.len() <= 8 && l.chars().all(|c| c.is_ascii_alphabetic()));
+    options
+}
+
+pub async fn list(query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    match post::list(&list_options(&query_string)).await {
         Ok(list) => Ok(wrap_json_data(&list)),
         Err(e) => Ok(wrap_json_err(500, e.0)),
     }
 }
 
-pub async fn list_by_tag(tag: String, pagination_type: String, post_id: u64) -> Result<impl Reply, Rejection> {
-    match post::list_by_tag(tag, &pagination_type, post_id, val::POSTS_PAGE_SIZE).await {
+pub async fn list_by_tag(tag: String, query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    match post::list_by_tag(tag, &list_options(&query_string)).await {
+        Ok(list) => Ok(wrap_json_data(&list)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn archive() -> Result<impl Reply, Rejection> {
+    match post::archive().await {
+        Ok(d) => Ok(wrap_json_data(&d)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn list_by_month(
+    year: i32,
+    month: u32,
+    query_string: HashMap<String, String>,
+) -> Result<impl Reply, Rejection> {
+    match post::list_by_month(year, month, &list_options(&query_string)).await {
+        Ok(list) => Ok(wrap_json_data(&list)),
+        Err(e) => Ok(wrap_json_err(400, e.0)),
+    }
+}
+
+pub async fn featured() -> Result<impl Reply, Rejection> {
+    match post::featured().await {
+        Ok(d) => Ok(wrap_json_data(&d)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn related(id: u64, query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    let amount = query_string.get("n").and_then(|n| n.parse::<u8>().ok()).unwrap_or(5);
+    match post::related(id as i64, amount).await {
+        Ok(d) => Ok(wrap_json_data(&d)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn search(query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    let keyword = query_string.get("q").map_or("", |q| q.as_str());
+    let page = query_string
+        .get("page")
+        .and_then(|p| p.parse::<u32>().ok())
+        .unwrap_or(1);
+    match search::search(keyword, page, val::POSTS_PAGE_SIZE).await {
         Ok(list) => Ok(wrap_json_data(&list)),
         Err(e) => Ok(wrap_json_err(500, e.0)),
     }
@@ -63,6 +143,7 @@ pub async fn show(
     token: Option<String>,
     id: u64,
     query_string: HashMap<String, String>,
+    visitor: Visitor,
 ) -> Result<impl Reply, Rejection> {
     let auth_result = status::check_auth(token);
     let edit = query_string.contains_key("edit");
@@ -73,12 +154,57 @@ pub async fn show(
     match post::show(id, editable).await {
         Ok(mut blog) => {
             blog.editable = editable;
+            // 管理员自己的阅读不统计
+            if auth_result.is_err() {
+                let visitor = visitor.with_referrer(query_string.get("ref"));
+                if let Err(e) = analytics::record_view(blog.id, &visitor).await {
+                    eprintln!("{:?}", e.0);
+                }
+            }
             Ok(wrap_json_data(&blog))
         },
         Err(e) => Ok(wrap_json_err(500, e.0)),
     }
 }
 
+pub async fn show_by_slug(
+    token: Option<String>,
+    slug: String,
+    query_string: HashMap<String, String>,
+    visitor: Visitor,
+) -> Result<WarpResponse, Rejection> {
+    match post::find_id_by_slug(&slug).await {
+        Ok(Some(id)) => {
+            return show(token, id as u64, query_string, visitor)
+                .await
+                .map(|r| r.into_response())
+        },
+        Ok(None) => {},
+        Err(e) => return Ok(wrap_json_err(500, e.0).into_response()),
+    }
+    match post::find_moved_slug(&slug).await {
+        Ok(Some(new_slug)) => Ok(moved_permanently("/post/slug/", &new_slug)),
+        Ok(None) => Ok(wrap_json_err(404, Error::CannotFoundPost).into_response()),
+        Err(e) => Ok(wrap_json_err(500, e.0).into_response()),
+    }
+}
+
+// 前端页面的永久链接，slug 改过的话 301 到新地址，否则交给前端路由
+pub async fn slug_page(slug: String) -> Result<WarpResponse, Rejection> {
+    if let Ok(Some(new_slug)) = post::find_moved_slug(&slug).await {
+        return Ok(moved_permanently("/p/", &new_slug));
+    }
+    Ok(warp::reply::html(super::index::INDEX_HTML).into_response())
+}
+
+fn moved_permanently(prefix: &str, slug: &str) -> WarpResponse {
+    let mut uri = String::with_capacity(64);
+    uri.push_str(prefix);
+    uri.push_str(slug);
+    let uri: Uri = uri.parse().unwrap();
+    warp::redirect::redirect(uri).into_response()
+}
+
 pub async fn delete(id: u64, user: Option<UserInfo>) -> Result<impl Reply, Rejection> {
     if user.is_some() {
         if let Err(e) = image::delete_post_images(id).await {
@@ -0,0 +1,44 @@
+use core::result::Result;
+
+use blog_common::{
+    dto::{series::SeriesData, user::UserInfo},
+    result::Error,
+};
+use warp::{reply::Response as WarpResponse, Rejection, Reply};
+
+use crate::{
+    db::series,
+    facade::{wrap_json_data, wrap_json_err},
+};
+
+pub async fn list() -> Result<impl Reply, Rejection> {
+    match series::list().await {
+        Ok(list) => Ok(wrap_json_data(&list)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+// 浏览器直接打开 /series/{name} 时交给前端路由，接口请求才返回 JSON
+pub async fn show(name: String, accept: Option<String>) -> Result<WarpResponse, Rejection> {
+    if accept.map_or(false, |a| a.contains("text/html")) {
+        return Ok(warp::reply::html(super::index::INDEX_HTML).into_response());
+    }
+    let name = match urlencoding::decode(&name) {
+        Ok(name) => name.into_owned(),
+        Err(_) => return Ok(wrap_json_err(400, Error::BadRequest).into_response()),
+    };
+    match series::detail(&name).await {
+        Ok(detail) => Ok(wrap_json_data(&detail).into_response()),
+        Err(e) => Ok(wrap_json_err(404, e.0).into_response()),
+    }
+}
+
+pub async fn save(user: Option<UserInfo>, data: SeriesData) -> Result<impl Reply, Rejection> {
+    if user.is_none() {
+        return Ok(wrap_json_err(403, Error::NotAuthed));
+    }
+    match series::save(data).await {
+        Ok(_) => Ok(wrap_json_data("Saved")),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
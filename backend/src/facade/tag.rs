@@ -1,5 +1,13 @@
 use core::{convert::Infallible, result::Result};
+use std::collections::HashMap;
 
+use blog_common::{
+    dto::{
+        tag::{TagData, TagMerge},
+        user::UserInfo,
+    },
+    result::Error,
+};
 use warp::{Rejection, Reply};
 
 use crate::{
@@ -20,3 +28,48 @@ pub async fn list() -> Result<impl Reply, Rejection> {
         Err(e) => Ok(wrap_json_err(500, e.0)),
     }
 }
+
+pub async fn list_detail() -> Result<impl Reply, Rejection> {
+    match tag::list_detail().await {
+        Ok(list) => Ok(wrap_json_data(&list)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn suggest(query_string: HashMap<String, String>) -> Result<impl Reply, Rejection> {
+    let prefix = query_string.get("q").map_or("", |q| q.as_str());
+    match tag::suggest(prefix).await {
+        Ok(list) => Ok(wrap_json_data(&list)),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn update(user: Option<UserInfo>, data: TagData) -> Result<impl Reply, Rejection> {
+    if user.is_none() {
+        return Ok(wrap_json_err(403, Error::NotAuthed));
+    }
+    match tag::update(data).await {
+        Ok(_) => Ok(wrap_json_data("Updated")),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn merge(user: Option<UserInfo>, data: TagMerge) -> Result<impl Reply, Rejection> {
+    if user.is_none() {
+        return Ok(wrap_json_err(403, Error::NotAuthed));
+    }
+    match tag::merge(data.from_id, data.into_id).await {
+        Ok(_) => Ok(wrap_json_data("Merged")),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
+
+pub async fn delete(id: i64, user: Option<UserInfo>) -> Result<impl Reply, Rejection> {
+    if user.is_none() {
+        return Ok(wrap_json_err(403, Error::NotAuthed));
+    }
+    match tag::delete(id).await {
+        Ok(_) => Ok(wrap_json_data("Deleted")),
+        Err(e) => Ok(wrap_json_err(500, e.0)),
+    }
+}
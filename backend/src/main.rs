@@ -1,6 +1,6 @@
 #![recursion_limit = "256"]
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, path::Path};
 
 use blog_backend::{db, service, util::result,config::{config_loader, self}};
 use clap::Parser;
@@ -19,6 +19,9 @@ fn main() -> result::Result<()> {
     pretty_env_logger::init();
     
     let mut args = crate::config_loader::Args::parse();
+    let migrate_only = args.migrate_only;
+    let (rerender, rerender_all) = (args.rerender, args.rerender_all);
+    let (backup, restore) = (args.backup, args.restore.clone());
     if args.config.is_some(){
         let config_result = config_loader::load_config(&mut args);
         match config_result{
@@ -30,6 +33,13 @@ fn main() -> result::Result<()> {
             },
             _=>()
         }
+        args.migrate_only |= migrate_only;
+        args.rerender |= rerender;
+        args.rerender_all |= rerender_all;
+        args.backup |= backup;
+        if restore.is_some() {
+            args.restore = restore;
+        }
     }
     let runtime = Builder::new_multi_thread()
         .worker_threads(4)
@@ -38,6 +48,48 @@ fn main() -> result::Result<()> {
         .thread_stack_size(1024 * 1024)
         .build()?;
 
+    if args.migrate_only {
+        println!("Migrating database...");
+        runtime.block_on(db::init_datasource());
+        runtime.block_on(db::shutdown());
+        println!("Bye...");
+        return Ok(());
+    }
+
+    if args.rerender || args.rerender_all {
+        println!("Re-rendering posts...");
+        runtime.block_on(db::init_datasource());
+        let report = runtime.block_on(db::rerender_posts(args.rerender_all));
+        runtime.block_on(db::shutdown());
+        let report = report?;
+        for f in report.failures.iter() {
+            eprintln!("Failed to re-render post {} ({}): {}", f.id, f.title, f.error);
+        }
+        println!("Re-rendered {} of {} post(s)", report.rendered, report.total);
+        println!("Bye...");
+        return Ok(());
+    }
+
+    if let Some(archive) = args.restore.as_ref() {
+        println!("Restoring from {}...", archive);
+        let moved = runtime.block_on(service::backup::restore(Path::new(archive)))?;
+        for path in moved.iter() {
+            println!("Previous data kept at {}", path.display());
+        }
+        println!("Bye...");
+        return Ok(());
+    }
+
+    if args.backup {
+        println!("Backing up...");
+        runtime.block_on(db::init_datasource());
+        let archive = runtime.block_on(service::backup::create(Path::new(service::backup::BACKUP_DIR)));
+        runtime.block_on(db::shutdown());
+        println!("Backup written to {}", archive?.display());
+        println!("Bye...");
+        return Ok(());
+    }
+
     let (tx, rx1) = broadcast::channel(2);
     let rx2 = tx.subscribe();
     runtime.spawn(async move {
@@ -69,6 +121,8 @@ fn main() -> result::Result<()> {
     } else {
         println!("Initializing database connection...");
         runtime.block_on(db::init_datasource());
+        runtime.spawn(service::backup::run_schedule());
+        service::server::trust_proxy(args.trust_proxy);
 
         println!("Creating server instance...");
         let mut servers: Vec<BoxFuture<()>> = Vec::new();
@@ -0,0 +1,11 @@
+ALTER TABLE posts ADD COLUMN slug TEXT(128);
+CREATE UNIQUE INDEX posts_slug_IDX ON posts (slug);
+
+CREATE TABLE post_slug_history (
+id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+slug TEXT(128) NOT NULL,
+post_id INTEGER NOT NULL,
+created_at INTEGER NOT NULL,
+CONSTRAINT "slug_UN" UNIQUE ("slug" ASC)
+);
+CREATE INDEX post_slug_history_post_id_IDX ON post_slug_history (post_id);
@@ -0,0 +1,2 @@
+-- rowid 就是博客的 id，中文按单字切分后再写入
+CREATE VIRTUAL TABLE posts_fts USING fts5(title, content, tokenize = 'unicode61 remove_diacritics 2');
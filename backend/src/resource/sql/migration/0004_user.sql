@@ -0,0 +1,7 @@
+CREATE TABLE user (
+id INTEGER NOT NULL PRIMARY KEY,
+email TEXT(128) NOT NULL,
+password TEXT(1024) NOT NULL,
+created_at INTEGER NOT NULL,
+CONSTRAINT "email_UN" UNIQUE ("email" ASC)
+);
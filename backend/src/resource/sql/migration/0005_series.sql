@@ -0,0 +1,15 @@
+CREATE TABLE series (
+id INTEGER NOT NULL PRIMARY KEY,
+name TEXT(128) NOT NULL,
+description TEXT(1024) DEFAULT '' NOT NULL,
+created_at INTEGER NOT NULL,
+CONSTRAINT "name_UN" UNIQUE ("name" ASC)
+);
+
+-- post_id 作为主键，一篇博客最多属于一个系列
+CREATE TABLE series_posts (
+post_id INTEGER NOT NULL PRIMARY KEY,
+series_id INTEGER NOT NULL,
+position INTEGER NOT NULL
+);
+CREATE INDEX series_posts_series_id_IDX ON series_posts (series_id, position);
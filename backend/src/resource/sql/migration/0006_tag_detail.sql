@@ -0,0 +1,5 @@
+ALTER TABLE tags ADD COLUMN description TEXT(1024) DEFAULT '' NOT NULL;
+ALTER TABLE tags ADD COLUMN colour TEXT(16) DEFAULT '' NOT NULL;
+
+-- 以前没有清理过的无用标签
+DELETE FROM tags WHERE NOT EXISTS (SELECT 1 FROM tags_usage u WHERE u.tag_id = tags.id);
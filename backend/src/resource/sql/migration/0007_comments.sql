@@ -0,0 +1,15 @@
+-- status: 0 待审核，1 已通过，2 垃圾评论
+CREATE TABLE comments (
+id INTEGER NOT NULL PRIMARY KEY,
+post_id INTEGER NOT NULL,
+parent_id INTEGER DEFAULT 0 NOT NULL,
+name TEXT(64) NOT NULL,
+email TEXT(128) DEFAULT '' NOT NULL,
+website TEXT(256) DEFAULT '' NOT NULL,
+markdown_content TEXT(4096) NOT NULL,
+rendered_content TEXT(16384) NOT NULL,
+status INTEGER DEFAULT 0 NOT NULL,
+created_at INTEGER NOT NULL
+);
+CREATE INDEX comments_post_id_IDX ON comments (post_id, status);
+CREATE INDEX comments_status_IDX ON comments (status, created_at);
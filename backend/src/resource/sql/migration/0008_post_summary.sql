@@ -0,0 +1,3 @@
+-- summary 是作者手动填写的摘要，excerpt 是保存时生成给列表页用的
+ALTER TABLE posts ADD COLUMN summary TEXT(1024) DEFAULT '' NOT NULL;
+ALTER TABLE posts ADD COLUMN excerpt TEXT(1024) DEFAULT '' NOT NULL;
@@ -0,0 +1,16 @@
+-- 只保存按天汇总的数字，不保存访客的 IP 或 User-Agent
+CREATE TABLE post_views (
+post_id INTEGER NOT NULL,
+day TEXT(10) NOT NULL,
+views INTEGER NOT NULL DEFAULT 0,
+PRIMARY KEY (post_id, day)
+);
+CREATE INDEX post_views_day_IDX ON post_views (day);
+
+CREATE TABLE referrer_views (
+domain TEXT(256) NOT NULL,
+day TEXT(10) NOT NULL,
+views INTEGER NOT NULL DEFAULT 0,
+PRIMARY KEY (domain, day)
+);
+CREATE INDEX referrer_views_day_IDX ON referrer_views (day);
@@ -0,0 +1,5 @@
+-- pinned 置顶在首页第一页，featured 显示在首页轮播图里
+ALTER TABLE posts ADD COLUMN pinned INTEGER DEFAULT 0 NOT NULL;
+ALTER TABLE posts ADD COLUMN featured INTEGER DEFAULT 0 NOT NULL;
+CREATE INDEX posts_pinned_IDX ON posts (pinned);
+CREATE INDEX posts_featured_IDX ON posts (featured);
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN toc TEXT(65535) DEFAULT '[]' NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN render_version TEXT(1024) DEFAULT '' NOT NULL;
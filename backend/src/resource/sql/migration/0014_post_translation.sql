@@ -0,0 +1,6 @@
+-- language 和 meta 里的语言一样，单独存一列方便按语言筛选；互为翻译的博客 translation_group 相同
+ALTER TABLE posts ADD COLUMN language TEXT(35) DEFAULT '' NOT NULL;
+ALTER TABLE posts ADD COLUMN translation_group INTEGER;
+UPDATE posts SET language = IFNULL(json_extract(meta, '$.language'), '') WHERE json_valid(meta);
+CREATE INDEX posts_language_IDX ON posts (language);
+CREATE INDEX posts_translation_group_IDX ON posts (translation_group);
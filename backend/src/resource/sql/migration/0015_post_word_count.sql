@@ -0,0 +1,3 @@
+-- reading_time 单位是分钟，旧博客在启动时补上
+ALTER TABLE posts ADD COLUMN word_count INTEGER DEFAULT 0 NOT NULL;
+ALTER TABLE posts ADD COLUMN reading_time INTEGER DEFAULT 0 NOT NULL;
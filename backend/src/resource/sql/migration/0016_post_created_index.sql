@@ -0,0 +1,2 @@
+-- 按月归档用 created_at 的范围查询
+CREATE INDEX posts_created_at_IDX ON posts (created_at);
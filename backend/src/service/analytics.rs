@@ -0,0 +1,166 @@
+use std::{collections::HashSet, net::SocketAddr};
+
+use ahash::RandomState;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use rand::{rngs::OsRng, RngCore};
+use regex::Regex;
+
+use crate::{db::analytics, util::result::Result};
+
+// 去重集合的上限，超过以后当天新的访客不再去重，直接计数
+const MAX_TRACKED_VISITS: usize = 100_000;
+const MAX_DOMAIN_LENGTH: usize = 253;
+
+lazy_static! {
+    static ref BOT_REGEX: Regex =
+        Regex::new(r"(?i)bot|crawl|spider|slurp|curl|wget|python|java/|go-http|httpclient|headless|preview|monitor")
+            .unwrap();
+    static ref VISITS: Mutex<DailyVisits> = Mutex::new(DailyVisits::new(String::new()));
+}
+
+// 盐只在内存里，每天换一次，所以存下来的哈希第二天就无法再和访客对应起来
+// 重启后当天的去重会重新开始，统计可能会略微偏多
+struct DailyVisits {
+    day: String,
+    hasher: RandomState,
+    seen: HashSet<(i64, u64)>,
+}
+
+impl DailyVisits {
+    fn new(day: String) -> Self {
+        DailyVisits {
+            day,
+            hasher: RandomState::with_seeds(OsRng.next_u64(), OsRng.next_u64(), OsRng.next_u64(), OsRng.next_u64()),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Visitor {
+    ip: String,
+    user_agent: String,
+    referrer: Option<String>,
+    host: String,
+}
+
+impl Visitor {
+    pub fn new(
+        remote: Option<SocketAddr>,
+        forwarded_for: Option<String>,
+        user_agent: Option<String>,
+        referrer: Option<String>,
+        host: Option<String>,
+    ) -> Self {
+        // 在反向代理后面时，取最初的客户端地址
+        let ip = forwarded_for
+            .as_deref()
+            .and_then(|f| f.split(',').next())
+            .map(|f| String::from(f.trim()))
+            .filter(|f| !f.is_empty())
+            .or_else(|| remote.map(|r| r.ip().to_string()))
+            .unwrap_or_default();
+        Visitor {
+            ip,
+            user_agent: user_agent.unwrap_or_default(),
+            referrer,
+            host: host.unwrap_or_default(),
+        }
+    }
+
+    // 单页应用里请求博客数据时，Referer 是自己的页面，真正的来源由前端通过参数带过来
+    pub fn with_referrer(mut self, referrer: Option<&String>) -> Self {
+        if let Some(r) = referrer {
+            self.referrer = Some(r.clone());
+        }
+        self
+    }
+
+    fn is_bot(&self) -> bool {
+        self.user_agent.is_empty() || BOT_REGEX.is_match(&self.user_agent)
+    }
+}
+
+fn host_name(s: &str) -> &str {
+    let s = s.trim();
+    let s = s.split_once("://").map_or(s, |(_, rest)| rest);
+    let s = s.split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or_default();
+    let s = s.rsplit_once('@').map_or(s, |(_, rest)| rest);
+    s.split(':').next().unwrap_or_default()
+}
+
+// 只保留来源的域名，站内跳转不算
+fn referrer_domain(referrer: &str, own_host: &str) -> Option<String> {
+    let domain = host_name(referrer).to_lowercase();
+    let domain = domain.strip_prefix("www.").unwrap_or(&domain);
+    let own_host = host_name(own_host).to_lowercase();
+    if domain.is_empty()
+        || domain.len() > MAX_DOMAIN_LENGTH
+        || domain == own_host.strip_prefix("www.").unwrap_or(&own_host)
+    {
+        return None;
+    }
+    Some(String::from(domain))
+}
+
+// 同一访客同一天多次阅读同一篇博客只算一次，day 和数据库里保存的日期一致
+fn first_visit_today(day: &str, post_id: i64, visitor: &Visitor) -> bool {
+    let mut visits = VISITS.lock();
+    if visits.day != day {
+        *visits = DailyVisits::new(String::from(day));
+    }
+    let hash = visits.hasher.hash_one((&visitor.ip, &visitor.user_agent));
+    if visits.seen.len() >= MAX_TRACKED_VISITS {
+        return !visits.seen.contains(&(post_id, hash));
+    }
+    visits.seen.insert((post_id, hash))
+}
+
+pub(crate) async fn record_view(post_id: i64, visitor: &Visitor) -> Result<()> {
+    if visitor.is_bot() {
+        return Ok(());
+    }
+    let day = analytics::today().await?;
+    if !first_visit_today(&day, post_id, visitor) {
+        return Ok(());
+    }
+    let referrer = visitor
+        .referrer
+        .as_deref()
+        .and_then(|r| referrer_domain(r, &visitor.host));
+    analytics::record_view(&day, post_id, referrer.as_deref()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn referrer_domain_strips_own_host() {
+        assert_eq!(
+            referrer_domain("https://www.Google.com/search?q=rust", "blog.example.com:9270"),
+            Some(String::from("google.com"))
+        );
+        assert_eq!(
+            referrer_domain("http://blog.example.com/posts/1", "blog.example.com:9270"),
+            None
+        );
+        assert_eq!(referrer_domain("", "blog.example.com"), None);
+    }
+
+    #[test]
+    fn visits_are_counted_once_per_stored_day() {
+        let visitor = Visitor::new(
+            None,
+            Some(String::from("10.0.0.1")),
+            Some(String::from("Firefox")),
+            None,
+            None,
+        );
+        assert!(first_visit_today("2022-01-31", 1, &visitor));
+        assert!(!first_visit_today("2022-01-31", 1, &visitor));
+        assert!(first_visit_today("2022-01-31", 2, &visitor));
+        assert!(first_visit_today("2022-02-01", 1, &visitor));
+    }
+}
@@ -0,0 +1,442 @@
+use core::time::Duration;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use blog_common::{result::Error, util::time};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    db::{self, management},
+    util::{common, result::Result, val},
+};
+
+pub const BACKUP_DIR: &'static str = "backup";
+const UPLOAD_DIR: &'static str = "upload";
+const MANIFEST_FILE: &'static str = "manifest.json";
+// 压缩包结构变化时加一，恢复时只接受认识的格式
+const FORMAT_VERSION: u32 = 1;
+const SECONDS_PER_DAY: u64 = 86400;
+// 1970-01-01 是星期四，加 3 天后按 7 天分组，每组从星期一开始
+const WEEK_OFFSET_DAYS: u64 = 3;
+
+lazy_static! {
+    static ref STATUS: Mutex<BackupStatus> = Mutex::new(BackupStatus::default());
+}
+
+// 定时备份的配置，以 JSON 保存在 settings 表
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub(crate) struct BackupSchedule {
+    pub enabled: bool,
+    // 每天执行的时间，HH:MM，UTC
+    pub time: String,
+    // 最近的几份都保留
+    pub keep_last: usize,
+    // 最近几天每天保留最新的一份
+    pub keep_daily: usize,
+    // 最近几周每周保留最新的一份
+    pub keep_weekly: usize,
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: String::from("03:00"),
+            keep_last: 3,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+impl BackupSchedule {
+    // 一天中的第几秒，时间格式不对时返回 None
+    pub fn seconds_of_day(&self) -> Option<u64> {
+        let (hour, minute) = self.time.split_once(':')?;
+        let (hour, minute) = (hour.parse::<u64>().ok()?, minute.parse::<u64>().ok()?);
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(hour * 3600 + minute * 60)
+    }
+}
+
+// 最近一次定时备份的结果，显示在管理页面上
+#[derive(Clone, Default, Serialize)]
+pub(crate) struct BackupStatus {
+    pub last_attempt: Option<u64>,
+    pub last_success: Option<u64>,
+    pub last_archive: Option<String>,
+    pub last_error: Option<String>,
+    pub next_run: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct BackupFile {
+    pub name: String,
+    pub created_at: u64,
+    pub size: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Manifest {
+    pub format_version: u32,
+    pub app_version: String,
+    // 备份时数据库已经执行到的迁移版本
+    pub schema_version: i64,
+    pub created_at: u64,
+    pub upload_files: usize,
+}
+
+fn add_dir(zip: &mut ZipWriter<File>, root: &Path, dir: &Path, options: FileOptions) -> Result<usize> {
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            count += add_dir(zip, root, &path, options)?;
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let name: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        zip.start_file(format!("{}/{}", UPLOAD_DIR, name.join("/")), options)?;
+        std::io::copy(&mut File::open(&path)?, zip)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn write_archive(target: &Path, snapshot: &Path, schema_version: i64) -> Result<()> {
+    let mut zip = ZipWriter::new(File::create(target)?);
+    let options = FileOptions::default().large_file(true);
+    zip.start_file(db::DATABASE_FILE, options)?;
+    std::io::copy(&mut File::open(snapshot)?, &mut zip)?;
+    let upload_dir = Path::new(UPLOAD_DIR);
+    let upload_files = if upload_dir.is_dir() {
+        add_dir(&mut zip, upload_dir, upload_dir, options)?
+    } else {
+        0
+    };
+    // 清单最后写，没有清单的压缩包说明备份没有完成
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: String::from(env!("CARGO_PKG_VERSION")),
+        schema_version,
+        created_at: time::unix_epoch_sec(),
+        upload_files,
+    };
+    zip.start_file(MANIFEST_FILE, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// 在 dir 目录下生成备份压缩包，包含数据库快照、上传的文件和清单，返回压缩包路径
+pub async fn create(dir: &Path) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+    // 同一秒里可能有计划备份和手动备份，加上随机后缀避免重名
+    let name = format!("backup-{}-{}", time::unix_epoch_sec(), &common::simple_uuid()[..8]);
+    let snapshot = dir.join(format!("{}.dat", name));
+    let partial = dir.join(format!("{}.zip.part", name));
+    let target = dir.join(format!("{}.zip", name));
+    let schema_version = db::snapshot(&snapshot).await?;
+
+    // 压缩上传的文件可能比较慢，放到阻塞线程池里做，不影响处理请求
+    let (s, p) = (snapshot.clone(), partial.clone());
+    let result = tokio::task::spawn_blocking(move || write_archive(&p, &s, schema_version))
+        .await
+        .unwrap_or_else(|e| Err(Error::BusinessException(e.to_string()).into()));
+    let _ = tokio::fs::remove_file(&snapshot).await;
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    // 写完再改名，目录里的 .zip 都是完整的备份
+    tokio::fs::rename(&partial, &target).await?;
+    Ok(target)
+}
+
+fn read_manifest(archive: &mut ZipArchive<File>) -> Result<Manifest> {
+    let mut manifest = String::new();
+    match archive.by_name(MANIFEST_FILE) {
+        Ok(mut f) => f.read_to_string(&mut manifest)?,
+        Err(_) => {
+            return Err(Error::BusinessException(String::from("备份里没有清单/Backup manifest not found")).into())
+        },
+    };
+    let manifest: Manifest = serde_json::from_str(&manifest)?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(Error::BusinessException(format!(
+            "不支持的备份格式/Unsupported backup format: {}",
+            manifest.format_version
+        ))
+        .into());
+    }
+    if manifest.schema_version > db::latest_schema_version() {
+        return Err(Error::BusinessException(format!(
+            "备份来自更新的版本，请先升级/Backup was created by a newer version: {}",
+            manifest.app_version
+        ))
+        .into());
+    }
+    Ok(manifest)
+}
+
+// 只解压数据库和 upload 目录，enclosed_name 会过滤掉 ../ 之类的路径
+fn extract(archive: &mut ZipArchive<File>, staging: &Path) -> Result<()> {
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let path = match file.enclosed_name() {
+            Some(p) => p.to_path_buf(),
+            None => continue,
+        };
+        if file.is_dir() || !(path == Path::new(db::DATABASE_FILE) || path.starts_with(UPLOAD_DIR)) {
+            continue;
+        }
+        let target = staging.join(&path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut File::create(&target)?)?;
+    }
+    if !staging.join(db::DATABASE_FILE).is_file() {
+        return Err(Error::BusinessException(String::from("备份里没有数据库/Database not found in backup")).into());
+    }
+    Ok(())
+}
+
+// 把当前的数据移到 .before-restore 后缀的位置，再把解压好的换进来，任何一步失败都会还原
+fn swap(staging: &Path, suffix: &str) -> Result<Vec<PathBuf>> {
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut placed: Vec<PathBuf> = Vec::new();
+    let mut result = Ok(());
+    for name in [db::DATABASE_FILE, UPLOAD_DIR] {
+        let current = PathBuf::from(name);
+        let old = PathBuf::from(format!("{}.{}", name, suffix));
+        let restored = staging.join(name);
+        if current.exists() {
+            if let Err(e) = std::fs::rename(&current, &old) {
+                result = Err(e);
+                break;
+            }
+            moved.push((current.clone(), old));
+        }
+        if restored.exists() {
+            if let Err(e) = std::fs::rename(&restored, &current) {
+                result = Err(e);
+                break;
+            }
+            placed.push(current);
+        }
+    }
+    if let Err(e) = result {
+        for current in placed.iter() {
+            let _ = if current.is_dir() {
+                std::fs::remove_dir_all(current)
+            } else {
+                std::fs::remove_file(current)
+            };
+        }
+        for (current, old) in moved.iter() {
+            let _ = std::fs::rename(old, current);
+        }
+        return Err(e.into());
+    }
+    Ok(moved.into_iter().map(|(_, old)| old).collect())
+}
+
+/// 从备份恢复数据库和上传的文件，必须在服务停止时执行；原来的数据会保留下来，返回它们的路径
+pub async fn restore(archive_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?)?;
+    let manifest = read_manifest(&mut archive)?;
+    println!(
+        "Restoring backup created at {} by version {} (schema V{}, {} upload file(s))",
+        manifest.created_at, manifest.app_version, manifest.schema_version, manifest.upload_files
+    );
+
+    // 解压到当前目录下，保证和数据在同一个文件系统里，改名是原子的
+    let suffix = format!("before-restore-{}", time::unix_epoch_sec());
+    let staging = PathBuf::from(format!("restore-{}", time::unix_epoch_sec()));
+    let result = restore_from(&mut archive, &staging, &suffix).await;
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    result
+}
+
+async fn restore_from(archive: &mut ZipArchive<File>, staging: &Path, suffix: &str) -> Result<Vec<PathBuf>> {
+    extract(archive, staging)?;
+    let applied = db::migrate_file(&staging.join(db::DATABASE_FILE)).await?;
+    if applied > 0 {
+        println!("Applied {} migration(s) to the restored database", applied);
+    }
+    swap(staging, suffix)
+}
+
+pub(crate) async fn load_schedule() -> Result<BackupSchedule> {
+    let schedule = management::get_setting(val::BACKUP_SCHEDULE)
+        .await?
+        .and_then(|s| serde_json::from_str(&s.content).ok())
+        .unwrap_or_default();
+    Ok(schedule)
+}
+
+pub(crate) fn status() -> BackupStatus {
+    STATUS.lock().clone()
+}
+
+/// 目录里 create 生成的备份，按时间从新到旧排列
+pub(crate) fn list(dir: &Path) -> Vec<BackupFile> {
+    let mut files: Vec<BackupFile> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().into_owned();
+                // backup-时间戳-随机后缀.zip，旧版本没有后缀
+                let created_at = name
+                    .strip_prefix("backup-")?
+                    .strip_suffix(".zip")?
+                    .split('-')
+                    .next()?
+                    .parse::<u64>()
+                    .ok()?;
+                let size = e.metadata().map_or(0, |m| m.len());
+                Some(BackupFile { name, created_at, size })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    files
+}
+
+// 每个时间段里保留最新的一份，只看最近 count 个有备份的时间段
+fn newest_per_period(sorted: &[u64], period_days: u64, offset_days: u64, count: usize, keep: &mut HashSet<u64>) {
+    let mut periods: HashSet<u64> = HashSet::new();
+    for t in sorted {
+        let period = (t / SECONDS_PER_DAY + offset_days) / period_days;
+        if periods.len() < count && periods.insert(period) {
+            keep.insert(*t);
+        }
+    }
+}
+
+// 三条规则保留的备份取并集，返回需要保留的备份时间
+fn retained(timestamps: &[u64], schedule: &BackupSchedule) -> HashSet<u64> {
+    let mut sorted = timestamps.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    // 至少保留刚生成的那份
+    let mut keep: HashSet<u64> = sorted.iter().take(schedule.keep_last.max(1)).copied().collect();
+    newest_per_period(&sorted, 1, 0, schedule.keep_daily, &mut keep);
+    newest_per_period(&sorted, 7, WEEK_OFFSET_DAYS, schedule.keep_weekly, &mut keep);
+    keep
+}
+
+// 按保留规则删除旧备份，手动备份也在同一个目录里，一起参与清理
+async fn prune(dir: &Path, schedule: &BackupSchedule) -> Result<usize> {
+    let files = list(dir);
+    let timestamps: Vec<u64> = files.iter().map(|f| f.created_at).collect();
+    let keep = retained(&timestamps, schedule);
+    let mut removed = 0;
+    for f in files.iter().filter(|f| !keep.contains(&f.created_at)) {
+        tokio::fs::remove_file(dir.join(&f.name)).await?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+async fn run_scheduled(dir: &Path, schedule: &BackupSchedule) {
+    STATUS.lock().last_attempt = Some(time::unix_epoch_sec());
+    let result = match create(dir).await {
+        Ok(archive) => {
+            println!("Scheduled backup written to {}", archive.display());
+            {
+                let mut status = STATUS.lock();
+                status.last_success = Some(time::unix_epoch_sec());
+                status.last_archive = archive.file_name().map(|n| n.to_string_lossy().into_owned());
+            }
+            prune(dir, schedule).await.map(|removed| {
+                if removed > 0 {
+                    println!("Removed {} old backup(s)", removed);
+                }
+            })
+        },
+        Err(e) => Err(e),
+    };
+    let error = result.err().map(|e| e.0.to_string());
+    if let Some(e) = error.as_ref() {
+        eprintln!("Scheduled backup failed: {}", e);
+    }
+    STATUS.lock().last_error = error;
+}
+
+/// 在服务的 tokio 运行时里执行，每分钟检查一次是否到了备份时间，修改配置后不需要重启
+pub async fn run_schedule() {
+    let dir = Path::new(BACKUP_DIR);
+    // 重启后不重复执行当天已经做过的备份
+    let mut last_run = list(dir).first().map_or(0, |f| f.created_at);
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        let schedule = match load_schedule().await {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Loading backup schedule failed: {:?}", e.0);
+                continue;
+            },
+        };
+        let at = match schedule.seconds_of_day() {
+            Some(at) if schedule.enabled => at,
+            _ => {
+                STATUS.lock().next_run = None;
+                continue;
+            },
+        };
+        let now = time::unix_epoch_sec();
+        let due = now - now % SECONDS_PER_DAY + at;
+        if now >= due && last_run < due {
+            last_run = now;
+            run_scheduled(dir, &schedule).await;
+        }
+        STATUS.lock().next_run = Some(if now >= due { due + SECONDS_PER_DAY } else { due });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retention_keeps_last_daily_and_weekly() {
+        let day = SECONDS_PER_DAY;
+        // 1970-01-05 是星期一，从那天起连续 21 天，每天 03:00 和 15:00 各一份
+        let timestamps: Vec<u64> = (4..25)
+            .flat_map(|d| [d * day + 3 * 3600, d * day + 15 * 3600])
+            .collect();
+        let schedule = BackupSchedule {
+            enabled: true,
+            keep_last: 3,
+            keep_daily: 2,
+            keep_weekly: 3,
+            ..Default::default()
+        };
+        let mut keep: Vec<u64> = retained(&timestamps, &schedule).into_iter().collect();
+        keep.sort_unstable();
+        let expected = vec![
+            10 * day + 15 * 3600,
+            17 * day + 15 * 3600,
+            23 * day + 15 * 3600,
+            24 * day + 3 * 3600,
+            24 * day + 15 * 3600,
+        ];
+        assert_eq!(keep, expected);
+    }
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, io::Write, path::Path};
+
+use blog_common::{dto::post::TocEntry, result::Error, util::time};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use uuid::Uuid;
+use v_htmlescape::escape;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    db::{model::Post, post},
+    service::highlight,
+    util::{self, result::Result},
+};
+
+const MIMETYPE: &'static str = "application/epub+zip";
+const CONTAINER_XML: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+    <rootfiles>
+        <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+    </rootfiles>
+</container>
+"#;
+const STYLE_CSS: &'static str =
+    "body { line-height: 1.6; } img { max-width: 100%; } pre { white-space: pre-wrap; } .date { color: #888; }\n";
+
+lazy_static! {
+    static ref IMG_REGEX: Regex = Regex::new(r#"<img\s[^>]*?src="([^"]+)"[^>]*>"#).unwrap();
+}
+
+#[derive(Clone)]
+pub struct EpubOptions {
+    pub title: String,
+    pub language: String,
+    pub tag: Option<String>,
+    // 发表时间范围，包含 from 不包含 to
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+struct Image {
+    id: String,
+    href: String,
+    media_type: &'static str,
+    data: Vec<u8>,
+}
+
+// 书里引用到的 upload 目录下的图片，同一张图片只打包一次
+#[derive(Default)]
+struct Images {
+    items: Vec<Image>,
+    by_path: HashMap<String, usize>,
+}
+
+impl Images {
+    // 返回图片的序号，不是本地图片或者文件不存在时返回 None
+    fn add(&mut self, src: &str) -> Option<usize> {
+        let path = src.trim_start_matches('/');
+        if !path.starts_with("upload/") || path.contains("..") {
+            return None;
+        }
+        if let Some(i) = self.by_path.get(path) {
+            return Some(*i);
+        }
+        let ext = path.rsplit_once('.').map_or("", |(_, e)| e).to_lowercase();
+        let media_type = media_type(&ext)?;
+        let data = std::fs::read(path).ok()?;
+        let index = self.items.len();
+        let id = format!("img-{}", index + 1);
+        self.items.push(Image {
+            href: format!("images/{}.{}", id, ext),
+            id,
+            media_type,
+            data,
+        });
+        self.by_path.insert(String::from(path), index);
+        Some(index)
+    }
+}
+
+fn media_type(ext: &str) -> Option<&'static str> {
+    match ext {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    }
+}
+
+// 和 service::import 的 days_from_civil 相反，把天数换成年月日
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+fn format_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn chapter_href(post: &Post) -> String {
+    format!("posts/{}.xhtml", post.id)
+}
+
+// 外部图片不能放进 EPUB，换成链接；本地图片改成书里的路径
+fn rewrite_images(html: &str, images: &mut Images) -> String {
+    IMG_REGEX
+        .replace_all(html, |caps: &Captures| {
+            let src = &caps[1];
+            match images.add(src) {
+                Some(i) => caps[0].replacen(src, &format!("../{}", images.items[i].href), 1),
+                None if src.starts_with("http") => format!("<a href=\"{}\">{}</a>", src, src),
+                None => String::new(),
+            }
+        })
+        .into_owned()
+}
+
+fn chapter(post: &Post, language: &str, images: &mut Images) -> String {
+    let title = escape(&post.title);
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+    <meta charset="UTF-8"/>
+    <title>{title}</title>
+    <link rel="stylesheet" type="text/css" href="../style.css"/>
+    <link rel="stylesheet" type="text/css" href="../highlight.css"/>
+</head>
+<body>
+<section epub:type="chapter">
+<h1>{title}</h1>
+<p class="date">{date}</p>
+{content}
+</section>
+</body>
+</html>
+"#,
+        lang = escape(language),
+        title = title,
+        date = format_date(post.created_at),
+        content = rewrite_images(&post.rendered_content, images),
+    )
+}
+
+fn nav_entries(entries: &[TocEntry], href: &str, out: &mut String) {
+    if entries.is_empty() {
+        return;
+    }
+    out.push_str("<ol>");
+    for e in entries {
+        out.push_str(&format!(
+            "<li><a href=\"{}#{}\">{}</a>",
+            href,
+            e.anchor,
+            escape(&e.title)
+        ));
+        nav_entries(&e.children, href, out);
+        out.push_str("</li>");
+    }
+    out.push_str("</ol>");
+}
+
+// 目录：每篇博客一项，下面是博客里的标题
+fn nav(posts: &[Post], options: &EpubOptions) -> String {
+    let mut items = String::new();
+    for post in posts {
+        let href = chapter_href(post);
+        items.push_str(&format!("<li><a href=\"{}\">{}</a>", href, escape(&post.title)));
+        nav_entries(&post.toc(), &href, &mut items);
+        items.push_str("</li>\n");
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+    <meta charset="UTF-8"/>
+    <title>{title}</title>
+</head>
+<body>
+<nav epub:type="toc" id="toc">
+<h1>{title}</h1>
+<ol>
+{items}</ol>
+</nav>
+</body>
+</html>
+"#,
+        lang = escape(&options.language),
+        title = escape(&options.title),
+        items = items,
+    )
+}
+
+fn cover(language: &str, href: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+    <meta charset="UTF-8"/>
+    <title>Cover</title>
+</head>
+<body>
+<section epub:type="cover">
+<img src="{href}" alt="Cover"/>
+</section>
+</body>
+</html>
+"#,
+        lang = escape(language),
+        href = href,
+    )
+}
+
+fn package(
+    posts: &[Post],
+    chapters: &[String],
+    images: &Images,
+    cover_image: Option<usize>,
+    options: &EpubOptions,
+) -> String {
+    let ids: Vec<String> = posts.iter().map(|p| p.id.to_string()).collect();
+    // 同样的博客导出的书标识相同，阅读器可以识别成同一本书的新版本
+    let identifier = Uuid::new_v5(&Uuid::NAMESPACE_URL, ids.join(",").as_bytes()).hyphenated();
+    let modified = time::unix_epoch_sec() as i64;
+    let (year, month, day) = civil_from_days(modified.div_euclid(86400));
+    let seconds = modified.rem_euclid(86400);
+    let modified = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    );
+
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    if cover_image.is_some() {
+        manifest.push_str("        <item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\"/>\n");
+        spine.push_str("        <itemref idref=\"cover\" linear=\"no\"/>\n");
+    }
+    spine.push_str("        <itemref idref=\"nav\"/>\n");
+    for (post, content) in posts.iter().zip(chapters) {
+        // 公式保存时已经转成了 MathML
+        let properties = if content.contains("<math") {
+            " properties=\"mathml\""
+        } else {
+            ""
+        };
+        manifest.push_str(&format!(
+            "        <item id=\"post-{id}\" href=\"{href}\" media-type=\"application/xhtml+xml\"{properties}/>\n",
+            id = post.id,
+            href = chapter_href(post),
+            properties = properties,
+        ));
+        spine.push_str(&format!("        <itemref idref=\"post-{}\"/>\n", post.id));
+    }
+    for (i, image) in images.items.iter().enumerate() {
+        let properties = if cover_image == Some(i) {
+            " properties=\"cover-image\""
+        } else {
+            ""
+        };
+        manifest.push_str(&format!(
+            "        <item id=\"{}\" href=\"{}\" media-type=\"{}\"{}/>\n",
+            image.id, image.href, image.media_type, properties
+        ));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{lang}">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+        <dc:identifier id="book-id">urn:uuid:{identifier}</dc:identifier>
+        <dc:title>{title}</dc:title>
+        <dc:language>{lang}</dc:language>
+        <meta property="dcterms:modified">{modified}</meta>
+    </metadata>
+    <manifest>
+        <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+        <item id="style" href="style.css" media-type="text/css"/>
+        <item id="highlight" href="highlight.css" media-type="text/css"/>
+{manifest}    </manifest>
+    <spine>
+{spine}    </spine>
+</package>
+"#,
+        lang = escape(&options.language),
+        identifier = identifier,
+        title = escape(&options.title),
+        modified = modified,
+        manifest = manifest,
+        spine = spine,
+    )
+}
+
+/// 生成 EPUB 3 电子书保存在 export 目录，返回文件名
+pub async fn export(options: &EpubOptions) -> Result<String> {
+    let posts = post::list_for_export(options.tag.as_deref(), options.from, options.to).await?;
+    if posts.is_empty() {
+        return Err(Error::BusinessException(String::from("没有符合条件的博客/No posts matched")).into());
+    }
+    let theme = highlight::current_theme().await?;
+
+    let export_dir = std::env::current_dir()?.join("export");
+    if !export_dir.exists() {
+        tokio::fs::create_dir(export_dir.as_path()).await?;
+    }
+    let mut filename = util::common::simple_uuid();
+    filename.push_str(".epub");
+
+    // 读图片和压缩都是阻塞操作，放到阻塞线程池里做
+    let path = export_dir.join(filename.as_str());
+    let (p, options) = (path.clone(), options.clone());
+    let result = tokio::task::spawn_blocking(move || write_book(&p, &posts, &options, &theme))
+        .await
+        .unwrap_or_else(|e| Err(Error::BusinessException(e.to_string()).into()));
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(filename)
+}
+
+fn write_book(path: &Path, posts: &[Post], options: &EpubOptions, theme: &str) -> Result<()> {
+    let mut images = Images::default();
+    // 用第一张能找到的标题图做封面
+    let cover_image = posts.iter().find_map(|p| images.add(&p.title_image));
+    let chapters: Vec<String> = posts
+        .iter()
+        .map(|p| chapter(p, &options.language, &mut images))
+        .collect();
+
+    let file = std::fs::File::create(path)?;
+    let mut zip = ZipWriter::new(file);
+
+    // mimetype 必须是第一个文件，并且不能压缩
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+    let options_deflated = FileOptions::default();
+    let mut add = |name: &str, data: &[u8]| -> Result<()> {
+        zip.start_file(name, options_deflated)?;
+        zip.write_all(data)?;
+        Ok(())
+    };
+    add("META-INF/container.xml", CONTAINER_XML.as_bytes())?;
+    add(
+        "OEBPS/content.opf",
+        package(posts, &chapters, &images, cover_image, options).as_bytes(),
+    )?;
+    add("OEBPS/nav.xhtml", nav(posts, options).as_bytes())?;
+    add("OEBPS/style.css", STYLE_CSS.as_bytes())?;
+    add("OEBPS/highlight.css", highlight::theme_css(theme).as_bytes())?;
+    if let Some(i) = cover_image {
+        add(
+            "OEBPS/cover.xhtml",
+            cover(&options.language, &images.items[i].href).as_bytes(),
+        )?;
+    }
+    for (post, content) in posts.iter().zip(chapters.iter()) {
+        add(&format!("OEBPS/{}", chapter_href(post)), content.as_bytes())?;
+    }
+    for image in images.items.iter() {
+        add(&format!("OEBPS/{}", image.href), &image.data)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1614834367), "2021-03-04");
+        assert_eq!(format_date(951782400), "2000-02-29");
+    }
+}
@@ -7,11 +7,19 @@ use tera::Tera;
 use zip::write::FileOptions;
 
 use crate::db::{management, model::Post, post};
+use crate::service::highlight;
 use crate::util::{self, result::Result};
 
 static HUGO_TEMPLATE: &'static str = include_str!("../resource/static-site/template/hugo.txt");
 static GIT_PAGES_DETAIL_HTML: &'static str = include_str!("../resource/page/git-pages-detail.html");
 static RENDER_TEMPLATE_HTML: &'static str = include_str!("../resource/page/export-template.html");
+static TAGS_HTML: &'static str = include_str!("../resource/page/tags.html");
+static COMMENTS_HTML: &'static str = include_str!("../resource/page/comments.html");
+static ANALYTICS_HTML: &'static str = include_str!("../resource/page/analytics.html");
+static MARKDOWN_HTML: &'static str = include_str!("../resource/page/markdown.html");
+static BACKUP_HTML: &'static str = include_str!("../resource/page/backup.html");
+static POSTS_HTML: &'static str = include_str!("../resource/page/posts.html");
+static HIGHLIGHT_CSS_FILE: &'static str = "highlight.css";
 
 lazy_static! {
     pub static ref TEMPLATES: Tera = {
@@ -25,6 +33,24 @@ lazy_static! {
         if let Err(e) = tera.add_raw_template("export-template.html", RENDER_TEMPLATE_HTML) {
             eprintln!("{:?}", e);
         }
+        if let Err(e) = tera.add_raw_template("tags.html", TAGS_HTML) {
+            eprintln!("{:?}", e);
+        }
+        if let Err(e) = tera.add_raw_template("comments.html", COMMENTS_HTML) {
+            eprintln!("{:?}", e);
+        }
+        if let Err(e) = tera.add_raw_template("analytics.html", ANALYTICS_HTML) {
+            eprintln!("{:?}", e);
+        }
+        if let Err(e) = tera.add_raw_template("markdown.html", MARKDOWN_HTML) {
+            eprintln!("{:?}", e);
+        }
+        if let Err(e) = tera.add_raw_template("backup.html", BACKUP_HTML) {
+            eprintln!("{:?}", e);
+        }
+        if let Err(e) = tera.add_raw_template("posts.html", POSTS_HTML) {
+            eprintln!("{:?}", e);
+        }
         tera
     };
 }
@@ -33,6 +59,14 @@ fn render(post: &Post, template_name: &str, template: Option<&String>) -> Result
     let mut context = tera::Context::new();
     context.insert("title", &post.title);
     context.insert("content", &post.markdown_content);
+    context.insert("rendered_content", &post.rendered_content);
+    context.insert("slug", &post.slug);
+    context.insert("meta", &post.meta());
+    context.insert("lang", &post.language);
+    // 互为翻译的博客 translationKey 相同
+    context.insert("translation_key", &post.translation_group.map(|g| g.to_string()));
+    context.insert("word_count", &post.word_count);
+    context.insert("reading_time", &post.reading_time);
     let r = if template.is_some() {
         tera::Tera::one_off(template.unwrap(), &context, true)
     } else {
@@ -81,7 +115,16 @@ where
 
 pub async fn hugo() -> Result<String> {
     let posts = post::all().await?;
+    hugo_zip(&posts).await
+}
+
+// 后台批量导出选中的博客
+pub async fn hugo_selection(ids: &[i64]) -> Result<String> {
+    let posts = post::list_by_ids(ids).await?;
+    hugo_zip(&posts).await
+}
 
+async fn hugo_zip(posts: &Vec<Post>) -> Result<String> {
     let export_dir = std::env::current_dir()?.join("export");
     if !export_dir.exists() {
         tokio::fs::create_dir(export_dir.as_path()).await?;
@@ -99,7 +142,7 @@ pub async fn hugo() -> Result<String> {
         Ok(())
     };
 
-    write_posts(&posts, zip_file, "md")?;
+    write_posts(posts, zip_file, "md")?;
 
     /*
     let mut file_name = String::with_capacity(32);
@@ -134,6 +177,12 @@ pub async fn git(git: &GitRepositoryInfo, push_info: &GitPushInfo) -> Result<()>
     } else {
         None
     };
+    // 导出的 HTML 不依赖博客服务，把代码高亮的样式一起写进去
+    if push_info.render_html {
+        let theme = highlight::current_theme().await?;
+        path.set_file_name(HIGHLIGHT_CSS_FILE);
+        std::fs::write(path.as_path(), highlight::theme_css(&theme))?;
+    }
     let (template_name, file_ext) = if one_off_template.is_some() {
         ("one_off_template", "html")
     } else {
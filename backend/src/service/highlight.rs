@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use comrak::adapters::SyntaxHighlighterAdapter;
+use lazy_static::lazy_static;
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+use crate::{
+    db::management,
+    util::{result::Result, val},
+};
+
+// 前缀避免和 bulma 的 class（比如 .title、.tag）冲突
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+pub(crate) const DEFAULT_THEME: &'static str = "InspiredGitHub";
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+}
+
+// 只输出 class，颜色由主题 CSS 决定，所以换主题不需要重新渲染博客
+pub(crate) struct ClassedHighlighter;
+
+impl SyntaxHighlighterAdapter for ClassedHighlighter {
+    fn highlight(&self, lang: Option<&str>, code: &str) -> String {
+        let syntax = lang
+            .and_then(|l| SYNTAX_SET.find_syntax_by_token(l))
+            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAX_SET, CLASS_STYLE);
+        for line in LinesWithEndings::from(code) {
+            if let Err(e) = generator.parse_html_for_line_which_includes_newline(line) {
+                eprintln!("{:?}", e);
+                return v_htmlescape::escape(code).to_string();
+            }
+        }
+        generator.finalize()
+    }
+
+    fn build_pre_tag(&self, attributes: &HashMap<String, String>) -> String {
+        let mut attributes = attributes.clone();
+        attributes.insert(String::from("class"), String::from("hl-code"));
+        opening_tag("pre", &attributes)
+    }
+
+    fn build_code_tag(&self, attributes: &HashMap<String, String>) -> String {
+        opening_tag("code", attributes)
+    }
+}
+
+fn opening_tag(tag: &str, attributes: &HashMap<String, String>) -> String {
+    let mut s = format!("<{}", tag);
+    // 排序后输出，保证同样的内容渲染结果一致
+    let mut attributes: Vec<(&String, &String)> = attributes.iter().collect();
+    attributes.sort();
+    for (name, value) in attributes {
+        s.push_str(&format!(" {}=\"{}\"", name, v_htmlescape::escape(value)));
+    }
+    s.push('>');
+    s
+}
+
+pub(crate) fn themes() -> Vec<&'static str> {
+    THEME_SET.themes.keys().map(String::as_str).collect()
+}
+
+pub(crate) fn is_valid_theme(name: &str) -> bool {
+    THEME_SET.themes.contains_key(name)
+}
+
+pub(crate) async fn current_theme() -> Result<String> {
+    let theme = management::get_setting(val::HIGHLIGHT_THEME)
+        .await?
+        .map(|s| s.content)
+        .filter(|t| is_valid_theme(t))
+        .unwrap_or_else(|| String::from(DEFAULT_THEME));
+    Ok(theme)
+}
+
+pub(crate) fn theme_css(name: &str) -> String {
+    let theme = THEME_SET
+        .themes
+        .get(name)
+        .unwrap_or_else(|| &THEME_SET.themes[DEFAULT_THEME]);
+    css_for_theme_with_class_style(theme, CLASS_STYLE).unwrap_or_else(|e| {
+        eprintln!("{:?}", e);
+        String::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_escapes_and_uses_classes() {
+        let html = ClassedHighlighter.highlight(Some("rust"), "let a = \"<b>\";\n");
+        assert!(html.contains("class=\"hl-"));
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(!html.contains("style="));
+    }
+}
@@ -0,0 +1,296 @@
+use blog_common::{
+    dto::{
+        import::{ImportReport, ImportStatus, ImportedFile},
+        post::{PostData, PostMeta},
+    },
+    util::time,
+};
+use serde::Deserialize;
+
+use super::{parse_date, SourceFiles};
+use crate::{db::post, util::result::Result};
+
+// 有这些目录时只导入里面的文件，避免把 README 之类的也当成博客
+const CONTENT_DIRS: [&'static str; 3] = ["content", "_posts", "_drafts"];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Terms {
+    List(Vec<String>),
+    // Jekyll 允许用空格分隔
+    Text(String),
+}
+
+impl Default for Terms {
+    fn default() -> Self {
+        Terms::List(Vec::new())
+    }
+}
+
+impl Terms {
+    fn to_vec(&self) -> Vec<String> {
+        match self {
+            Terms::List(l) => l.clone(),
+            Terms::Text(t) => t.split_whitespace().map(String::from).collect(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FrontMatter {
+    title: Option<String>,
+    date: Option<String>,
+    tags: Terms,
+    // Jekyll 常用分类代替标签，一起作为标签导入
+    categories: Terms,
+    draft: bool,
+    // Jekyll 用 published: false 表示草稿
+    published: Option<bool>,
+    slug: Option<String>,
+    summary: Option<String>,
+    description: Option<String>,
+}
+
+impl FrontMatter {
+    fn tags(&self) -> Vec<String> {
+        let mut tags = self.tags.to_vec();
+        for c in self.categories.to_vec() {
+            if !tags.contains(&c) {
+                tags.push(c);
+            }
+        }
+        tags
+    }
+}
+
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => s.into(),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(d) => d.to_string().into(),
+        toml::Value::Array(a) => a.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(t) => serde_json::Value::Object(t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
+
+// --- 之间是 YAML（Jekyll、Hugo），+++ 之间是 TOML（Hugo）
+fn parse_front_matter(text: &str) -> core::result::Result<(FrontMatter, &str), String> {
+    let (delimiter, is_toml) = if text.starts_with("---") {
+        ("---", false)
+    } else if text.starts_with("+++") {
+        ("+++", true)
+    } else {
+        return Ok((FrontMatter::default(), text));
+    };
+    let rest = match text[3..].strip_prefix("\r\n").or_else(|| text[3..].strip_prefix('\n')) {
+        Some(r) => r,
+        None => return Ok((FrontMatter::default(), text)),
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            let front = &rest[..offset];
+            let value = if is_toml {
+                toml_to_json(toml::from_str(front).map_err(|e| e.to_string())?)
+            } else {
+                serde_yaml::from_str(front).map_err(|e| e.to_string())?
+            };
+            let front_matter = if value.is_null() {
+                FrontMatter::default()
+            } else {
+                serde_json::from_value(value).map_err(|e| e.to_string())?
+            };
+            return Ok((front_matter, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    Err(String::from("Front matter 没有结束/Front matter is not closed"))
+}
+
+// Jekyll 的文件名是 2006-01-02-slug.md
+fn jekyll_file_name(stem: &str) -> (Option<i64>, &str) {
+    match stem.get(..10).and_then(parse_date) {
+        Some(date) if stem.get(10..11) == Some("-") => (Some(date), &stem[11..]),
+        _ => (None, stem),
+    }
+}
+
+fn is_markdown(path: &str) -> bool {
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+fn in_content_dir(path: &str) -> bool {
+    path.split('/').any(|s| CONTENT_DIRS.contains(&s))
+}
+
+pub(crate) async fn import(files: &SourceFiles) -> ImportReport {
+    let has_content_dir = files.keys().any(|p| is_markdown(p) && in_content_dir(p));
+    let mut report = ImportReport::default();
+    for (path, data) in files.iter() {
+        if !is_markdown(path) || (has_content_dir && !in_content_dir(path)) {
+            continue;
+        }
+        report.push(import_file(files, path, data).await);
+    }
+    report
+}
+
+async fn import_file(files: &SourceFiles, path: &str, data: &[u8]) -> ImportedFile {
+    let mut file = ImportedFile {
+        path: String::from(path),
+        status: ImportStatus::Failed,
+        post_id: None,
+        title: String::new(),
+        messages: Vec::new(),
+        created_at: None,
+        tags: Vec::new(),
+        slug: None,
+    };
+    let text = match std::str::from_utf8(data) {
+        Ok(t) => t.trim_start_matches('\u{feff}'),
+        Err(_) => {
+            file.messages
+                .push(String::from("文件不是 UTF-8 编码/File is not UTF-8"));
+            return file;
+        },
+    };
+    let (front_matter, content) = match parse_front_matter(text) {
+        Ok(r) => r,
+        Err(e) => {
+            file.messages.push(e);
+            return file;
+        },
+    };
+
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = file_name.rsplit_once('.').map_or(file_name, |(s, _)| s);
+    // Hugo 的 page bundle 用目录名
+    let stem = if stem.eq("index") {
+        path.rsplit('/').nth(1).unwrap_or(stem)
+    } else {
+        stem
+    };
+    let (file_date, file_slug) = jekyll_file_name(stem);
+    file.title = front_matter.title.clone().unwrap_or_else(|| String::from(file_slug));
+
+    let draft = front_matter.draft || front_matter.published == Some(false) || path.contains("_drafts/");
+    if file_name.starts_with("_index.") || draft {
+        file.status = ImportStatus::Skipped;
+        file.messages.push(if draft {
+            String::from("草稿/Draft")
+        } else {
+            String::from("列表页/Section page")
+        });
+        return file;
+    }
+
+    let created_at = match front_matter.date.as_deref() {
+        Some(date) => match parse_date(date) {
+            Some(d) => d,
+            None => {
+                file.messages
+                    .push(format!("无法识别的日期/Unrecognized date: {}", date));
+                file_date.unwrap_or(time::unix_epoch_sec() as i64)
+            },
+        },
+        None => file_date.unwrap_or(time::unix_epoch_sec() as i64),
+    };
+
+    let mut front_matter = front_matter;
+    if front_matter.slug.is_none() {
+        front_matter.slug = Some(String::from(file_slug));
+    }
+    file.created_at = Some(created_at);
+    file.tags = front_matter.tags();
+    file.slug = front_matter.slug.clone();
+
+    let post_id = match post::new_post().await {
+        Ok(id) => id,
+        Err(e) => {
+            file.messages.push(e.0.to_string());
+            return file;
+        },
+    };
+    match save(
+        files,
+        path,
+        post_id,
+        front_matter,
+        content,
+        created_at,
+        &mut file.messages,
+    )
+    .await
+    {
+        Ok(_) => {
+            file.status = ImportStatus::Imported;
+            file.post_id = Some(post_id);
+        },
+        Err(e) => {
+            file.messages.push(e.0.to_string());
+            super::discard_post(post_id as u64).await;
+        },
+    }
+    file
+}
+
+async fn save(
+    files: &SourceFiles,
+    path: &str,
+    post_id: i64,
+    front_matter: FrontMatter,
+    content: &str,
+    created_at: i64,
+    messages: &mut Vec<String>,
+) -> Result<()> {
+    let content = super::rewrite_images(
+        Some(post_id as u64),
+        content,
+        |link| super::find_source_image(files, path, link),
+        messages,
+    )
+    .await;
+    let tags = front_matter.tags();
+    let description = front_matter.description.unwrap_or_default();
+    let post_data = PostData {
+        id: post_id,
+        title: front_matter
+            .title
+            .or_else(|| front_matter.slug.clone())
+            .unwrap_or_default(),
+        content,
+        tags: if tags.is_empty() { None } else { Some(tags) },
+        slug: front_matter.slug,
+        // 没有摘要时用描述
+        summary: front_matter.summary.or_else(|| Some(description.clone())),
+        meta: PostMeta {
+            description,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    post::save(post_data).await?;
+    post::set_created_at(post_id, created_at).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_yaml_and_toml_front_matter() {
+        let (yaml, body) =
+            parse_front_matter("---\ntitle: Hello\ntags: rust web\ndate: 2020-01-02\n---\nBody").unwrap();
+        assert_eq!(yaml.title.as_deref(), Some("Hello"));
+        assert_eq!(yaml.tags.to_vec(), vec!["rust", "web"]);
+        assert_eq!(body, "Body");
+
+        let (toml, _) =
+            parse_front_matter("+++\ntitle = \"Hi\"\ndate = 2020-01-02T03:04:05Z\ndraft = true\n+++\n").unwrap();
+        assert!(toml.draft);
+        assert_eq!(parse_date(&toml.date.unwrap()), Some(1577934245));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    path::Path,
+};
+
+use blog_common::result::Error;
+use lazy_static::lazy_static;
+use regex::Regex;
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    db::post,
+    service::image,
+    util::{
+        io::{self, SupportFileType},
+        result::Result,
+    },
+};
+
+pub(crate) mod markdown;
+pub(crate) mod wordpress;
+
+// 压缩包或目录里的相对路径（用 / 分隔）到文件内容，按路径排序保证导入顺序固定
+pub(crate) type SourceFiles = BTreeMap<String, Vec<u8>>;
+
+const IMPORT_EXTENSIONS: [&'static str; 6] = ["md", "markdown", "png", "jpg", "jpeg", "gif"];
+// 生成的站点、主题和依赖里的 Markdown 不是博客
+const SKIPPED_DIRS: [&'static str; 5] = ["public", "resources", "themes", "node_modules", "_site"];
+
+lazy_static! {
+    // Markdown 图片和 <img src>、{{< figure src >}} 里的地址
+    static ref IMAGE_LINK_REGEX: Regex = Regex::new(r#"!\[[^\]]*\]\(\s*<?([^)\s>]+)|\bsrc=["']([^"']+)["']"#).unwrap();
+}
+
+fn extension(path: &str) -> String {
+    path.rsplit_once('.')
+        .map(|(_, e)| e.to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+fn is_wanted(path: &str) -> bool {
+    let skipped = path.split('/').any(|s| s.starts_with('.') || SKIPPED_DIRS.contains(&s));
+    !skipped && IMPORT_EXTENSIONS.contains(&extension(path).as_str())
+}
+
+pub(crate) fn read_zip(data: &[u8]) -> Result<SourceFiles> {
+    let invalid =
+        |e: zip::result::ZipError| Error::BusinessException(format!("无法读取压缩包/Invalid zip archive: {}", e));
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).map_err(invalid)?;
+    let mut files = SourceFiles::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(invalid)?;
+        let name = match entry.enclosed_name() {
+            Some(n) => n.to_string_lossy().replace('\\', "/"),
+            None => continue,
+        };
+        if !entry.is_file() || !is_wanted(&name) {
+            continue;
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        files.insert(name, data);
+    }
+    Ok(files)
+}
+
+pub(crate) fn read_dir(root: &Path) -> Result<SourceFiles> {
+    if !root.is_dir() {
+        return Err(Error::BusinessException(format!("目录不存在/Directory not found: {}", root.display())).into());
+    }
+    let mut files = SourceFiles::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            if path.is_dir() {
+                dirs.push(path);
+            } else if is_wanted(&name) {
+                files.insert(name, std::fs::read(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn timezone_offset(tz: &str) -> Option<i64> {
+    let sign = match tz.chars().next() {
+        None => return Some(0),
+        Some('Z') if tz.len() == 1 => return Some(0),
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None,
+    };
+    let digits: Vec<i64> = tz[1..].chars().filter_map(|c| c.to_digit(10)).map(i64::from).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    Some(sign * ((digits[0] * 10 + digits[1]) * 3600 + (digits[2] * 10 + digits[3]) * 60))
+}
+
+// 支持 2006-01-02、2006-01-02T15:04:05+08:00 和 Jekyll 的 2006-01-02 15:04:05 +0800，没有时区时按 UTC
+pub(crate) fn parse_date(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let number = |from: usize, to: usize| s.get(from..to).and_then(|n| n.parse::<i64>().ok());
+    if s.get(4..5) != Some("-") || s.get(7..8) != Some("-") {
+        return None;
+    }
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86400;
+    let rest = s[10..].trim_start_matches(|c| c == 'T' || c == ' ');
+    if rest.is_empty() {
+        return Some(seconds);
+    }
+    let number = |from: usize, to: usize| rest.get(from..to).and_then(|n| n.parse::<i64>().ok());
+    if rest.get(2..3) != Some(":") {
+        return None;
+    }
+    let (hour, minute) = (number(0, 2)?, number(3, 5)?);
+    let (second, tz) = if rest.get(5..6) == Some(":") {
+        (number(6, 8)?, &rest[8..])
+    } else {
+        (0, &rest[5..])
+    };
+    // 忽略小数秒
+    let tz = tz.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit()).trim();
+    seconds += hour * 3600 + minute * 60 + second;
+    Some(seconds - timezone_offset(tz)?)
+}
+
+// 处理 ./ 和 ../，越过根目录时返回 None
+fn normalize(dir: &str, link: &str) -> Option<String> {
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    for s in link.split('/') {
+        match s {
+            "" | "." => {},
+            ".." => {
+                segments.pop()?;
+            },
+            _ => segments.push(s),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+// 相对地址按文件所在目录找；/ 开头的先找 Hugo 的 static 目录，再找站点根目录
+fn find_image<'a>(files: &'a SourceFiles, source_path: &str, link: &str) -> Option<(&'a String, &'a Vec<u8>)> {
+    let link = link.split(|c| c == '?' || c == '#').next().unwrap_or_default();
+    let link = urlencoding::decode(link).map_or(String::from(link), |l| l.into_owned());
+    let candidates = match link.strip_prefix('/') {
+        Some(absolute) => vec![format!("static/{}", absolute), String::from(absolute)],
+        None => {
+            let dir = source_path.rsplit_once('/').map_or("", |(d, _)| d);
+            normalize(dir, &link).into_iter().collect()
+        },
+    };
+    candidates.iter().find_map(|c| {
+        let suffix = format!("/{}", c);
+        files
+            .get_key_value(c)
+            .or_else(|| files.iter().find(|(k, _)| k.ends_with(&suffix)))
+    })
+}
+
+async fn copy_image(post_id: u64, path: &str, data: &[u8]) -> Result<String> {
+    let ext = extension(path);
+    ext.parse::<SupportFileType>()?;
+    // 用完整路径算文件名，不同目录下的同名图片不会互相覆盖
+    let (mut file, _path_buf, relative_path) = io::get_save_file(post_id, path, &ext, true).await?;
+    file.write_all(data).await?;
+    Ok(format!("/{}", relative_path))
+}
+
+pub(crate) enum ImageSource {
+    // 外部图片，保留原地址
+    Remote,
+    Missing,
+    Local(String, Vec<u8>),
+}
+
+fn is_remote(link: &str) -> bool {
+    link.contains("://") || link.starts_with("//") || link.starts_with("data:")
+}
+
+pub(crate) fn find_source_image(files: &SourceFiles, source_path: &str, link: &str) -> ImageSource {
+    if is_remote(link) {
+        return ImageSource::Remote;
+    }
+    find_image(files, source_path, link).map_or(ImageSource::Missing, |(path, data)| {
+        ImageSource::Local(path.clone(), data.clone())
+    })
+}
+
+/// 把博客里引用的本地图片复制到 upload 目录，并改写成新的地址；找不到的图片保留原地址并记录下来。
+/// post_id 为 None 时只是预览，不复制也不改写
+pub(crate) async fn rewrite_images<F>(
+    post_id: Option<u64>,
+    content: &str,
+    find: F,
+    messages: &mut Vec<String>,
+) -> String
+where
+    F: Fn(&str) -> ImageSource,
+{
+    let mut copied: HashMap<String, String> = HashMap::new();
+    let mut rewritten = String::with_capacity(content.len());
+    let mut last = 0;
+    for captures in IMAGE_LINK_REGEX.captures_iter(content) {
+        let link = captures.get(1).or_else(|| captures.get(2)).unwrap();
+        let (path, data) = match find(link.as_str()) {
+            ImageSource::Remote => continue,
+            ImageSource::Missing => {
+                messages.push(format!("图片不存在/Image not found: {}", link.as_str()));
+                continue;
+            },
+            ImageSource::Local(path, data) => (path, data),
+        };
+        let post_id = match post_id {
+            Some(id) => id,
+            None => continue,
+        };
+        let url = match copied.get(&path) {
+            Some(url) => url.clone(),
+            None => match copy_image(post_id, &path, &data).await {
+                Ok(url) => {
+                    copied.insert(path, url.clone());
+                    url
+                },
+                Err(e) => {
+                    messages.push(format!("{}: {}", link.as_str(), e.0));
+                    continue;
+                },
+            },
+        };
+        rewritten.push_str(&content[last..link.start()]);
+        rewritten.push_str(&url);
+        last = link.end();
+    }
+    rewritten.push_str(&content[last..]);
+    rewritten
+}
+
+// 保存失败时把已经创建的空博客和复制过去的图片删掉
+pub(crate) async fn discard_post(post_id: u64) {
+    if let Err(e) = post::delete(post_id).await {
+        eprintln!("{:?}", e.0);
+    }
+    if let Err(e) = image::delete_post_images(post_id).await {
+        eprintln!("{:?}", e.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2021-03-04T05:06:07+08:00"), Some(1614805567));
+        assert_eq!(parse_date("2019-12-31 23:00:00 -0530"), Some(1577853000));
+        assert_eq!(parse_date("2021-03-04T05:06:07.123Z"), Some(1614805567 + 8 * 3600));
+        assert_eq!(parse_date("March 4"), None);
+    }
+
+    #[test]
+    fn find_image_resolves_relative_and_static_paths() {
+        let mut files = SourceFiles::new();
+        files.insert(String::from("site/content/post/a/cover.png"), vec![]);
+        files.insert(String::from("site/static/images/logo.png"), vec![]);
+        let source = "site/content/post/a/index.md";
+        assert_eq!(
+            find_image(&files, source, "./cover.png").unwrap().0,
+            "site/content/post/a/cover.png"
+        );
+        assert_eq!(
+            find_image(&files, source, "/images/logo.png?v=1").unwrap().0,
+            "site/static/images/logo.png"
+        );
+        assert!(find_image(&files, source, "../../../../../x.png").is_none());
+    }
+}
@@ -0,0 +1,343 @@
+use std::path::Path;
+
+use blog_common::{
+    dto::{
+        import::{ImportReport, ImportStatus, ImportedFile},
+        post::PostData,
+    },
+    result::Error,
+    util::time,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use roxmltree::{Document, Node};
+
+use super::{parse_date, ImageSource};
+use crate::{db::post, util::result::Result};
+
+// WXR 1.0 到 1.2 的命名空间只有版本号不同，按片段匹配
+const WP_NAMESPACE: &'static str = "wordpress.org/export/";
+const CONTENT_NAMESPACE: &'static str = "purl.org/rss/1.0/modules/content/";
+const EXCERPT_NAMESPACE: &'static str = "/excerpt/";
+const UPLOADS_PATH: &'static str = "/wp-content/uploads/";
+const MORE_TAG: &'static str = "<!--more-->";
+// 分类和标签都作为标签导入，默认分类除外
+const DEFAULT_CATEGORY: &'static str = "uncategorized";
+const BLOCK_TAGS: [&'static str; 16] = [
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "pre",
+    "table",
+    "figure",
+    "hr",
+];
+
+lazy_static! {
+    // WordPress 生成的缩略图，如 photo-300x200.jpg
+    static ref RESIZED_IMAGE_REGEX: Regex = Regex::new(r"-\d+x\d+(\.[A-Za-z]+)$").unwrap();
+}
+
+struct Item {
+    link: String,
+    title: String,
+    content: String,
+    excerpt: String,
+    slug: Option<String>,
+    date: String,
+    tags: Vec<String>,
+    status: String,
+}
+
+fn child_text<'a>(node: Node<'a, '_>, namespace: Option<&str>, name: &str) -> &'a str {
+    node.children()
+        .find(|c| {
+            c.tag_name().name() == name
+                && match namespace {
+                    Some(ns) => c.tag_name().namespace().map_or(false, |n| n.contains(ns)),
+                    None => c.tag_name().namespace().is_none(),
+                }
+        })
+        .and_then(|c| c.text())
+        .unwrap_or_default()
+}
+
+fn parse_item(node: Node) -> Item {
+    let slug = child_text(node, Some(WP_NAMESPACE), "post_name");
+    // 中文等别名是 URL 编码过的
+    let slug = urlencoding::decode(slug).map_or(String::from(slug), |s| s.into_owned());
+    let title = child_text(node, None, "title").trim();
+    // 草稿的 GMT 时间是 0000-00-00 00:00:00
+    let date = match child_text(node, Some(WP_NAMESPACE), "post_date_gmt") {
+        d if d.is_empty() || d.starts_with("0000") => child_text(node, Some(WP_NAMESPACE), "post_date"),
+        d => d,
+    };
+    let mut tags: Vec<String> = Vec::new();
+    for category in node.children().filter(|c| c.has_tag_name("category")) {
+        if !matches!(category.attribute("domain"), Some("category") | Some("post_tag")) {
+            continue;
+        }
+        if category.attribute("nicename") == Some(DEFAULT_CATEGORY) {
+            continue;
+        }
+        let name = category.text().unwrap_or_default().trim();
+        if !name.is_empty() && !tags.iter().any(|t| t.eq(name)) {
+            tags.push(String::from(name));
+        }
+    }
+    Item {
+        link: String::from(child_text(node, None, "link")),
+        title: String::from(if title.is_empty() { slug.as_str() } else { title }),
+        content: String::from(child_text(node, Some(CONTENT_NAMESPACE), "encoded")),
+        excerpt: String::from(child_text(node, Some(EXCERPT_NAMESPACE), "encoded").trim()),
+        slug: if slug.is_empty() { None } else { Some(slug) },
+        date: String::from(date),
+        tags,
+        status: String::from(child_text(node, Some(WP_NAMESPACE), "status")),
+    }
+}
+
+// 只要博客，附件、页面和菜单等都不导入
+fn parse(xml: &str) -> Result<Vec<Item>> {
+    let document = Document::parse(xml)
+        .map_err(|e| Error::BusinessException(format!("无法解析 WXR 文件/Invalid WXR file: {}", e)))?;
+    let items = document
+        .descendants()
+        .filter(|n| n.has_tag_name("item"))
+        .filter(|n| child_text(*n, Some(WP_NAMESPACE), "post_type") == "post")
+        .map(parse_item)
+        .collect();
+    Ok(items)
+}
+
+fn is_block(chunk: &str) -> bool {
+    let chunk = chunk.to_lowercase();
+    chunk.starts_with("<!--")
+        || BLOCK_TAGS.iter().any(|t| {
+            chunk
+                .strip_prefix('<')
+                .and_then(|c| c.strip_prefix(t))
+                .map_or(false, |r| {
+                    r.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace())
+                })
+        })
+}
+
+// WordPress 保存的正文没有 <p>，显示时才按空行分段，转换前先补上
+fn autop(html: &str) -> String {
+    let html = html.replace("\r\n", "\n");
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut pre: Option<String> = None;
+    for chunk in html.split("\n\n") {
+        // <pre> 里的空行要保留
+        if let Some(p) = pre.as_mut() {
+            p.push_str("\n\n");
+            p.push_str(chunk);
+            if chunk.contains("</pre>") {
+                paragraphs.push(pre.take().unwrap());
+            }
+            continue;
+        }
+        let chunk = chunk.trim();
+        if chunk.is_empty() {
+            continue;
+        }
+        if chunk.contains("<pre") && !chunk.contains("</pre>") {
+            pre = Some(String::from(chunk));
+        } else if is_block(chunk) {
+            paragraphs.push(String::from(chunk));
+        } else {
+            paragraphs.push(format!("<p>{}</p>", chunk.replace('\n', "<br/>\n")));
+        }
+    }
+    paragraphs.extend(pre);
+    paragraphs.join("\n")
+}
+
+fn to_markdown(html: &str) -> String {
+    html.split(MORE_TAG)
+        .map(|part| html2md::parse_html(&autop(part)).trim().to_string())
+        .collect::<Vec<String>>()
+        .join(&format!("\n\n{}\n\n", MORE_TAG))
+}
+
+// 把 /wp-content/uploads/ 后面的路径对应到上传的目录里，缩略图找不到时用原图
+fn find_attachment(uploads: Option<&Path>, link: &str) -> ImageSource {
+    let (uploads, rest) = match (uploads, link.split_once(UPLOADS_PATH)) {
+        (Some(uploads), Some((_, rest))) => (uploads, rest),
+        _ => return ImageSource::Remote,
+    };
+    let rest = rest.split(|c| c == '?' || c == '#').next().unwrap_or_default();
+    let rest = urlencoding::decode(rest).map_or(String::from(rest), |r| r.into_owned());
+    let rest = match super::normalize("", &rest) {
+        Some(r) if !r.is_empty() => r,
+        _ => return ImageSource::Missing,
+    };
+    let original = RESIZED_IMAGE_REGEX.replace(&rest, "$1").into_owned();
+    for candidate in [rest, original] {
+        if let Ok(data) = std::fs::read(uploads.join(&candidate)) {
+            return ImageSource::Local(candidate, data);
+        }
+    }
+    ImageSource::Missing
+}
+
+/// dry_run 为 true 时只预览，不写数据库也不复制附件
+pub(crate) async fn import(xml: &str, uploads: Option<&Path>, dry_run: bool) -> Result<ImportReport> {
+    if let Some(dir) = uploads {
+        if !dir.is_dir() {
+            return Err(Error::BusinessException(format!("目录不存在/Directory not found: {}", dir.display())).into());
+        }
+    }
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+    for item in parse(xml)? {
+        report.push(import_item(item, uploads, dry_run).await);
+    }
+    Ok(report)
+}
+
+async fn import_item(item: Item, uploads: Option<&Path>, dry_run: bool) -> ImportedFile {
+    let mut file = ImportedFile {
+        path: item.link.clone(),
+        status: ImportStatus::Failed,
+        post_id: None,
+        title: item.title.clone(),
+        messages: Vec::new(),
+        created_at: None,
+        tags: item.tags.clone(),
+        slug: item.slug.clone(),
+    };
+    if item.status != "publish" {
+        file.status = ImportStatus::Skipped;
+        file.messages.push(format!("未发布/Not published: {}", item.status));
+        return file;
+    }
+    let created_at = match parse_date(&item.date) {
+        Some(d) => d,
+        None => {
+            file.messages
+                .push(format!("无法识别的日期/Unrecognized date: {}", item.date));
+            time::unix_epoch_sec() as i64
+        },
+    };
+    file.created_at = Some(created_at);
+    let content = to_markdown(&item.content);
+
+    if dry_run {
+        super::rewrite_images(
+            None,
+            &content,
+            |link| find_attachment(uploads, link),
+            &mut file.messages,
+        )
+        .await;
+        file.status = ImportStatus::Imported;
+        return file;
+    }
+
+    let post_id = match post::new_post().await {
+        Ok(id) => id,
+        Err(e) => {
+            file.messages.push(e.0.to_string());
+            return file;
+        },
+    };
+    match save(post_id, item, &content, created_at, uploads, &mut file.messages).await {
+        Ok(_) => {
+            file.status = ImportStatus::Imported;
+            file.post_id = Some(post_id);
+        },
+        Err(e) => {
+            file.messages.push(e.0.to_string());
+            super::discard_post(post_id as u64).await;
+        },
+    }
+    file
+}
+
+async fn save(
+    post_id: i64,
+    item: Item,
+    content: &str,
+    created_at: i64,
+    uploads: Option<&Path>,
+    messages: &mut Vec<String>,
+) -> Result<()> {
+    let content = super::rewrite_images(
+        Some(post_id as u64),
+        content,
+        |link| find_attachment(uploads, link),
+        messages,
+    )
+    .await;
+    let post_data = PostData {
+        id: post_id,
+        title: item.title,
+        content,
+        // 保存时会记录标签的使用次数
+        tags: if item.tags.is_empty() { None } else { Some(item.tags) },
+        slug: item.slug,
+        summary: if item.excerpt.is_empty() {
+            None
+        } else {
+            Some(to_markdown(&item.excerpt))
+        },
+        ..Default::default()
+    };
+    post::save(post_data).await?;
+    post::set_created_at(post_id, created_at).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wxr_items() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/"
+    xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+    <item>
+        <title>Hello</title>
+        <link>https://example.com/hello/</link>
+        <content:encoded><![CDATA[First line
+
+<pre>a
+
+b</pre>]]></content:encoded>
+        <excerpt:encoded><![CDATA[]]></excerpt:encoded>
+        <wp:post_date><![CDATA[2021-03-04 13:06:07]]></wp:post_date>
+        <wp:post_date_gmt><![CDATA[2021-03-04 05:06:07]]></wp:post_date_gmt>
+        <wp:post_name><![CDATA[%e4%bd%a0%e5%a5%bd]]></wp:post_name>
+        <wp:status><![CDATA[publish]]></wp:status>
+        <wp:post_type><![CDATA[post]]></wp:post_type>
+        <category domain="category" nicename="uncategorized"><![CDATA[Uncategorized]]></category>
+        <category domain="post_tag" nicename="rust"><![CDATA[Rust]]></category>
+    </item>
+    <item>
+        <title>photo</title>
+        <wp:post_type><![CDATA[attachment]]></wp:post_type>
+    </item>
+</channel>
+</rss>"#;
+        let items = parse(xml).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].slug.as_deref(), Some("你好"));
+        assert_eq!(items[0].tags, vec!["Rust"]);
+        assert_eq!(parse_date(&items[0].date), Some(1614834367));
+        assert_eq!(autop(&items[0].content), "<p>First line</p>\n<pre>a\n\nb</pre>");
+    }
+}
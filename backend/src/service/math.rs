@@ -0,0 +1,204 @@
+use latex2mathml::{latex_to_mathml, DisplayStyle};
+
+// comrak 会过滤掉原始 HTML，所以先把公式换成占位符，渲染完再替换成 MathML
+// U+FFFC 几乎不会出现在正文里，两边都加上避免 1 和 10 混淆
+const PLACEHOLDER_MARK: char = '\u{FFFC}';
+
+pub(crate) struct Formula {
+    // 带分隔符的原文，用在目录标题里
+    pub source: String,
+    pub html: String,
+    block: bool,
+}
+
+fn placeholder(index: usize) -> String {
+    format!("{}MATH{}{}", PLACEHOLDER_MARK, index, PLACEHOLDER_MARK)
+}
+
+fn to_mathml(tex: &str, source: &str, block: bool) -> String {
+    let style = if block {
+        DisplayStyle::Block
+    } else {
+        DisplayStyle::Inline
+    };
+    match latex_to_mathml(tex.trim(), style) {
+        Ok(m) => m,
+        // 解析失败时保留原文并标红，不影响保存
+        Err(e) => format!(
+            "<code class=\"math-error has-text-danger\" title=\"{}\">{}</code>",
+            v_htmlescape::escape(&e.to_string()),
+            v_htmlescape::escape(source)
+        ),
+    }
+}
+
+fn push_formula(out: &mut String, formulas: &mut Vec<Formula>, tex: &str, block: bool) {
+    let source = if block {
+        format!("$${}$$", tex)
+    } else {
+        format!("${}$", tex)
+    };
+    out.push_str(&placeholder(formulas.len()));
+    formulas.push(Formula {
+        html: to_mathml(tex, &source, block),
+        source,
+        block,
+    });
+}
+
+// 行内公式：$ 后面和结尾 $ 前面不能是空白，结尾 $ 后面不能是数字，这样 "$5 and $10" 不会被当成公式
+fn closing_dollar(chars: &[char], start: usize) -> Option<usize> {
+    if chars.get(start).map_or(true, |c| c.is_whitespace() || *c == '$') {
+        return None;
+    }
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            // 公式不会跨过行内代码
+            '`' => return None,
+            '$' if !chars[i - 1].is_whitespace() && !chars.get(i + 1).map_or(false, char::is_ascii_digit) => {
+                return Some(i)
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    None
+}
+
+fn extract_inline(line: &str, out: &mut String, formulas: &mut Vec<Formula>) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                out.push(chars[i]);
+                out.push(chars[i + 1]);
+                i += 2;
+            },
+            // 行内代码原样保留
+            '`' => {
+                let ticks = chars[i..].iter().take_while(|c| **c == '`').count();
+                let mut end = i + ticks;
+                while end < chars.len() {
+                    let run = chars[end..].iter().take_while(|c| **c == '`').count();
+                    if run == ticks {
+                        break;
+                    }
+                    end += run.max(1);
+                }
+                let end = (end + ticks).min(chars.len());
+                out.extend(&chars[i..end]);
+                i = end;
+            },
+            '$' => match closing_dollar(&chars, i + 1) {
+                Some(end) => {
+                    let tex: String = chars[i + 1..end].iter().collect();
+                    push_formula(out, formulas, &tex, false);
+                    i = end + 1;
+                },
+                None => {
+                    out.push('$');
+                    i += 1;
+                },
+            },
+            c => {
+                out.push(c);
+                i += 1;
+            },
+        }
+    }
+}
+
+/// 把 `$...$` 和 `$$...$$` 换成占位符，代码块和行内代码里的不处理
+pub(crate) fn extract(markdown: &str) -> (String, Vec<Formula>) {
+    let mut out = String::with_capacity(markdown.len());
+    let mut formulas: Vec<Formula> = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut block: Option<String> = None;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(f) = &fence {
+            if trimmed.starts_with(f.as_str()) {
+                fence = None;
+            }
+            out.push_str(line);
+            continue;
+        }
+        if let Some(tex) = block.as_mut() {
+            match trimmed.strip_suffix("$$") {
+                Some(rest) => {
+                    tex.push_str(rest);
+                    let tex = block.take().unwrap();
+                    push_formula(&mut out, &mut formulas, &tex, true);
+                    out.push('\n');
+                },
+                None => tex.push_str(line),
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = trimmed.chars().next().unwrap();
+            fence = Some(trimmed.chars().take_while(|c| *c == marker).collect());
+            out.push_str(line);
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("$$") {
+            match rest.strip_suffix("$$") {
+                Some(tex) => {
+                    push_formula(&mut out, &mut formulas, tex, true);
+                    out.push('\n');
+                },
+                None => block = Some(format!("{}\n", rest)),
+            }
+            continue;
+        }
+        extract_inline(line, &mut out, &mut formulas);
+    }
+    // 没有闭合的 $$ 按普通文本处理
+    if let Some(tex) = block {
+        out.push_str("$$");
+        out.push_str(&tex);
+    }
+    (out, formulas)
+}
+
+pub(crate) fn restore(text: &str, formulas: &[Formula], html: bool) -> String {
+    let mut text = String::from(text);
+    for (i, f) in formulas.iter().enumerate() {
+        let token = placeholder(i);
+        if !text.contains(&token) {
+            continue;
+        }
+        let replacement = if html { &f.html } else { &f.source };
+        // 独占一段的块级公式不需要外面的 <p>
+        if html && f.block {
+            text = text.replace(&format!("<p>{}</p>", token), replacement);
+        }
+        text = text.replace(&token, replacement);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_skips_code_and_prices() {
+        let markdown = "Price $5 and $10, `$x$` and $a^2$.\n\n```\n$$\n```\n$$\nx\n$$\n";
+        let (text, formulas) = extract(markdown);
+        assert_eq!(formulas.len(), 2);
+        assert!(text.starts_with("Price $5 and $10, `$x$` and \u{FFFC}MATH0\u{FFFC}."));
+        assert!(text.contains("```\n$$\n```\n\u{FFFC}MATH1\u{FFFC}\n"));
+        assert!(formulas[1].html.contains("display=\"block\""));
+    }
+
+    #[test]
+    fn invalid_formula_is_marked() {
+        let (_, formulas) = extract("$\\frac{1$");
+        assert!(formulas[0].html.contains("math-error"));
+        assert!(formulas[0].html.contains("$\\frac{1$"));
+    }
+}
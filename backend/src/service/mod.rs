@@ -1,6 +1,13 @@
+pub(crate) mod analytics;
 pub(crate) mod asset;
+pub mod backup;
+pub(crate) mod epub;
 pub(crate) mod export;
+pub(crate) mod highlight;
 pub(crate) mod git;
 pub(crate) mod image;
+pub(crate) mod import;
+pub(crate) mod math;
+pub(crate) mod render;
 pub mod server;
 pub mod status;
@@ -0,0 +1,319 @@
+use blog_common::{dto::post::TocEntry, result::Error};
+use comrak::{
+    arena_tree::Node,
+    format_html_with_plugins,
+    nodes::{Ast, NodeValue},
+    parse_document, Anchorizer, Arena, ComrakOptions, ComrakPlugins,
+};
+use core::cell::RefCell;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::management,
+    service::{highlight::ClassedHighlighter, math},
+    util::{result::Result, val},
+};
+
+// 手动分隔摘要和正文，两种写法都支持
+const MORE_MARKERS: [&'static str; 2] = ["<!--more-->", "<!-- more -->"];
+const EXCERPT_LENGTH: usize = 200;
+// 每分钟能读的中日韩文字数和其它语言的单词数
+const CJK_CHARS_PER_MINUTE: u32 = 300;
+const WORDS_PER_MINUTE: u32 = 200;
+// 渲染逻辑或 comrak、syntect、latex2mathml 升级导致输出变化时加一，旧博客会被重新渲染
+const RENDERER_VERSION: u32 = 1;
+
+// 可以在设置里开关的 Markdown 扩展，以 JSON 保存在 settings 表
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub(crate) struct MarkdownExtensions {
+    pub table: bool,
+    pub strikethrough: bool,
+    pub tasklist: bool,
+    pub footnotes: bool,
+    pub autolink: bool,
+    // :smile: 这样的 emoji 短代码
+    pub shortcodes: bool,
+    pub superscript: bool,
+    pub description_lists: bool,
+    // $...$ 和 $$...$$ 公式，保存时转成 MathML
+    pub math: bool,
+}
+
+impl Default for MarkdownExtensions {
+    fn default() -> Self {
+        Self {
+            table: true,
+            strikethrough: true,
+            tasklist: true,
+            footnotes: true,
+            autolink: true,
+            shortcodes: true,
+            superscript: false,
+            description_lists: false,
+            math: true,
+        }
+    }
+}
+
+impl MarkdownExtensions {
+    fn options(&self) -> ComrakOptions {
+        let mut options = ComrakOptions::default();
+        let extension = &mut options.extension;
+        extension.table = self.table;
+        extension.strikethrough = self.strikethrough;
+        extension.tasklist = self.tasklist;
+        extension.footnotes = self.footnotes;
+        extension.autolink = self.autolink;
+        extension.shortcodes = self.shortcodes;
+        extension.superscript = self.superscript;
+        extension.description_lists = self.description_lists;
+        // 标题总是带上 id，目录要用
+        extension.header_ids = Some(String::new());
+        options
+    }
+}
+
+pub(crate) async fn load_extensions() -> Result<MarkdownExtensions> {
+    let extensions = management::get_setting(val::MARKDOWN_EXTENSIONS)
+        .await?
+        .and_then(|s| serde_json::from_str(&s.content).ok())
+        .unwrap_or_default();
+    Ok(extensions)
+}
+
+// 渲染器版本加上扩展配置，任何一个变化都说明保存的 HTML 过期了
+pub(crate) fn render_version(extensions: &MarkdownExtensions) -> String {
+    format!("{}:{}", RENDERER_VERSION, serde_json::to_string(extensions).unwrap())
+}
+
+pub(crate) struct Rendered {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+pub(crate) fn render_markdown(markdown: &str, extensions: &MarkdownExtensions) -> Result<Rendered> {
+    let (markdown, formulas) = if extensions.math {
+        math::extract(markdown)
+    } else {
+        (String::from(markdown), Vec::new())
+    };
+    let options = extensions.options();
+    let arena = Arena::new();
+    let root = parse_document(&arena, &markdown, &options);
+
+    // 按文档顺序生成锚点，和 comrak 渲染时的去重规则保持一致
+    let mut anchorizer = Anchorizer::new();
+    let mut toc: Vec<TocEntry> = Vec::new();
+    for node in root.descendants() {
+        let level = match node.data.borrow().value {
+            NodeValue::Heading(ref h) => h.level as u8,
+            _ => continue,
+        };
+        let mut title = String::new();
+        heading_text(node, &mut title);
+        let entry = TocEntry {
+            level,
+            anchor: anchorizer.anchorize(title.clone()),
+            title: math::restore(&title, &formulas, false),
+            children: Vec::new(),
+        };
+        push_toc_entry(&mut toc, entry);
+    }
+
+    let highlighter = ClassedHighlighter;
+    let mut plugins = ComrakPlugins::default();
+    plugins.render.codefence_syntax_highlighter = Some(&highlighter);
+    let mut html = Vec::with_capacity(markdown.len() * 2);
+    format_html_with_plugins(root, &options, &mut html, &plugins).map_err(render_error)?;
+    let html = String::from_utf8(html).map_err(render_error)?;
+    Ok(Rendered {
+        html: math::restore(&html, &formulas, true),
+        toc,
+    })
+}
+
+fn render_error(e: impl std::fmt::Display) -> Error {
+    Error::BusinessException(format!("渲染失败/Render failed: {}", e))
+}
+
+// 和 comrak 生成 header id 时取文本的方式一样
+fn heading_text<'a>(node: &'a Node<'a, RefCell<Ast>>, text: &mut String) {
+    match node.data.borrow().value {
+        NodeValue::Text(ref t) => text.push_str(&String::from_utf8_lossy(t)),
+        NodeValue::Code(ref c) => text.push_str(&String::from_utf8_lossy(&c.literal)),
+        NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+        _ => {
+            for child in node.children() {
+                heading_text(child, text);
+            }
+        },
+    }
+}
+
+// 级别更低的标题挂到前一个标题下面
+fn push_toc_entry(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+    match entries.last_mut() {
+        Some(last) if last.level < entry.level => push_toc_entry(&mut last.children, entry),
+        _ => entries.push(entry),
+    }
+}
+
+fn collect_text<'a>(node: &'a Node<'a, RefCell<Ast>>, text: &mut String) {
+    let is_block = {
+        let value = &node.data.borrow().value;
+        match value {
+            NodeValue::Text(t) => text.push_str(&String::from_utf8_lossy(t)),
+            NodeValue::Code(c) => text.push_str(&String::from_utf8_lossy(&c.literal)),
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            // 代码块、HTML 和图片不适合放进摘要
+            NodeValue::CodeBlock(_) | NodeValue::HtmlBlock(_) | NodeValue::HtmlInline(_) | NodeValue::Image(_) => {
+                return
+            },
+            _ => {},
+        }
+        value.block()
+    };
+    for child in node.children() {
+        collect_text(child, text);
+    }
+    if is_block && !text.ends_with(' ') {
+        text.push(' ');
+    }
+}
+
+fn plain_text(markdown: &str) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, &ComrakOptions::default());
+    let mut text = String::with_capacity(markdown.len());
+    collect_text(root, &mut text);
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '。' | '！' | '？' | '；' | '.' | '!' | '?' | ';')
+}
+
+// 按字符截断，优先停在句子结尾，其次是单词边界；中文没有空格时直接按字符截
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return String::from(text);
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let min = cut.len() / 2;
+    if let Some((idx, c)) = cut.char_indices().rev().find(|(_, c)| is_sentence_end(*c)) {
+        if idx >= min {
+            return String::from(&cut[..idx + c.len_utf8()]);
+        }
+    }
+    let mut s = match cut.rfind(char::is_whitespace) {
+        Some(idx) if idx >= min => String::from(cut[..idx].trim_end()),
+        _ => cut,
+    };
+    s.push('…');
+    s
+}
+
+/// 列表页展示的摘要：优先用作者填写的，其次是 <!--more--> 之前的内容，最后自动截取
+pub(crate) fn excerpt(markdown: &str, summary: Option<&str>) -> String {
+    if let Some(summary) = summary.map(str::trim).filter(|s| !s.is_empty()) {
+        return String::from(summary);
+    }
+    for marker in MORE_MARKERS.iter() {
+        if let Some(idx) = markdown.find(marker) {
+            return plain_text(&markdown[..idx]);
+        }
+    }
+    truncate(&plain_text(markdown), EXCERPT_LENGTH)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2ffff}')
+}
+
+pub(crate) struct WordCount {
+    pub words: u32,
+    // 分钟，有内容时至少 1 分钟
+    pub reading_time: u32,
+}
+
+/// 统计正文的字数，中日韩文字一个字算一个，其它按单词算；代码块和图片不算
+pub(crate) fn word_count(markdown: &str) -> WordCount {
+    let mut cjk = 0u32;
+    let mut words = 0u32;
+    let mut in_word = false;
+    for c in plain_text(markdown).chars() {
+        if is_cjk(c) {
+            cjk += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        } else if !(in_word && matches!(c, '\'' | '’' | '-' | '_')) {
+            // don't、well-known 算一个单词
+            in_word = false;
+        }
+    }
+    let seconds = cjk * 60 / CJK_CHARS_PER_MINUTE + words * 60 / WORDS_PER_MINUTE;
+    let total = cjk + words;
+    WordCount {
+        words: total,
+        reading_time: if total == 0 { 0 } else { ((seconds + 59) / 60).max(1) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_multi_byte_text() {
+        let text = "中文".repeat(150);
+        let s = truncate(&text, EXCERPT_LENGTH);
+        assert_eq!(s.chars().count(), EXCERPT_LENGTH + 1);
+        assert!(s.ends_with('…'));
+    }
+
+    #[test]
+    fn truncate_at_sentence_end() {
+        let text = format!("{}。{}", "字".repeat(150), "句".repeat(100));
+        assert_eq!(truncate(&text, EXCERPT_LENGTH), format!("{}。", "字".repeat(150)));
+    }
+
+    #[test]
+    fn excerpt_honours_more_marker() {
+        let markdown = "# Title\n\nFirst **paragraph**.\n\n<!--more-->\n\nRest of the post.";
+        assert_eq!(excerpt(markdown, None), "Title First paragraph.");
+        assert_eq!(excerpt(markdown, Some("  Custom  ")), "Custom");
+    }
+
+    #[test]
+    fn count_cjk_by_character_and_latin_by_word() {
+        let count = word_count("# 你好 world\n\nIt's a well-known `fact`.\n\n```\nignored code\n```");
+        assert_eq!(count.words, 2 + 5);
+        assert_eq!(count.reading_time, 1);
+        assert_eq!(word_count("字".repeat(900).as_str()).reading_time, 3);
+        assert_eq!(word_count("").reading_time, 0);
+    }
+
+    #[test]
+    fn toc_nests_headings_with_unique_anchors() {
+        let markdown = "# Intro\n\n## Setup\n\n### `cargo` build\n\n## Setup\n\n# End";
+        let rendered = render_markdown(markdown, &MarkdownExtensions::default()).unwrap();
+        let toc = rendered.toc;
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].children.len(), 2);
+        assert_eq!(toc[0].children[0].children[0].title, "cargo build");
+        assert_eq!(toc[0].children[1].anchor, "setup-1");
+        assert!(rendered.html.contains("id=\"setup-1\""));
+        assert_eq!(toc[1].anchor, "end");
+    }
+}
@@ -1,26 +1,41 @@
 use std::vec::Vec;
-use std::{collections::HashMap, convert::Infallible, net::SocketAddr};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use futures::future::Future;
 use tokio::sync::broadcast::Receiver;
-use warp::{self, reject, Filter, Reply};
+use warp::{self, reject, Filter, Rejection, Reply};
 
 use blog_common::{
     dto::{
+        comment::CommentData,
         git::GitPushInfo,
         management::{AdminUser, Setting},
-        post::PostData,
+        post::{BulkRequest, PostData},
+        series::SeriesData,
+        tag::{TagData, TagMerge},
         user::UserInfo,
     },
     val,
 };
 
 use crate::{
-    facade::{self, asset, export, git, image, management, post, tag, user},
-    service::status,
+    facade::{self, asset, comment, export, git, image, management, post, series, tag, user},
+    service::{analytics::Visitor, status},
     util::result::Result,
 };
 
+// 只有部署在反向代理后面时 X-Forwarded-For 才可信，否则谁都可以伪造
+static TRUST_PROXY: AtomicBool = AtomicBool::new(false);
+
+pub fn trust_proxy(enabled: bool) {
+    TRUST_PROXY.store(enabled, Ordering::Relaxed);
+}
+
 #[derive(Debug)]
 struct FilterError;
 
@@ -61,6 +76,19 @@ fn auth() -> impl Filter<Extract = (Option<UserInfo>,), Error = Infallible> + Cl
     // })
 }
 
+// 统计阅读量用，不会保存原始的 IP 和 User-Agent
+fn visitor() -> impl Filter<Extract = (Visitor,), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and(
+            warp::header::optional::<String>("x-forwarded-for")
+                .map(|f: Option<String>| f.filter(|_| TRUST_PROXY.load(Ordering::Relaxed))),
+        )
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(warp::header::optional::<String>("referer"))
+        .and(warp::header::optional::<String>("host"))
+        .map(Visitor::new)
+}
+
 fn hsts_header_appender<F, T>(
     filter: F,
 ) -> impl Filter<Extract = (warp::reply::WithHeader<T>,)> + Clone + Send + Sync + 'static
@@ -259,21 +287,88 @@ pub fn blog_filter(
         .and(warp::path("top"))
         .and(warp::path::end())
         .and_then(tag::top);
+    let tags_detail = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path("detail"))
+        .and(warp::path::end())
+        .and_then(tag::list_detail);
+    let tag_suggest = warp::get()
+        .and(warp::path("tag"))
+        .and(warp::path("suggest"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(tag::suggest);
+    let tag_update = warp::post()
+        .and(warp::path("tag"))
+        .and(warp::path("update"))
+        .and(warp::path::end())
+        .and(auth())
+        .and(warp::body::json::<TagData>())
+        .and_then(tag::update);
+    let tag_merge = warp::post()
+        .and(warp::path("tag"))
+        .and(warp::path("merge"))
+        .and(warp::path::end())
+        .and(auth())
+        .and(warp::body::json::<TagMerge>())
+        .and_then(tag::merge);
+    let tag_delete = warp::get()
+        .and(warp::path("tag"))
+        .and(warp::path("delete"))
+        .and(warp::path::param::<i64>())
+        .and(warp::path::end())
+        .and(auth())
+        .and_then(tag::delete);
+    let management_tags = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and_then(management::show_tags_page);
     let post_list = warp::get()
         .and(warp::path("post"))
         .and(warp::path("list"))
-        .and(warp::path::param::<String>())
-        .and(warp::path::param::<u64>())
         .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
         .and_then(post::list);
     let post_list_by_tag = warp::get()
         .and(warp::path("post"))
         .and(warp::path("tag"))
         .and(warp::path::param::<String>())
-        .and(warp::path::param::<String>())
-        .and(warp::path::param::<u64>())
         .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
         .and_then(post::list_by_tag);
+    let post_archive = warp::get()
+        .and(warp::path("post"))
+        .and(warp::path("archive"))
+        .and(warp::path::end())
+        .and_then(post::archive);
+    let post_list_by_month = warp::get()
+        .and(warp::path("post"))
+        .and(warp::path("archive"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::param::<u32>())
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(post::list_by_month);
+    let post_featured = warp::get()
+        .and(warp::path("post"))
+        .and(warp::path("featured"))
+        .and(warp::path::end())
+        .and_then(post::featured);
+    let post_related = warp::get()
+        .and(warp::path("post"))
+        .and(warp::path("related"))
+        .and(warp::path::param::<u64>())
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(post::related);
+    let post_search = warp::get()
+        .and(warp::path("post"))
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(post::search);
     let post_new = warp::get()
         .and(warp::path("post"))
         .and(warp::path("new"))
@@ -301,7 +396,154 @@ pub fn blog_filter(
         .and(warp::path::param::<u64>())
         .and(warp::query::<HashMap<String, String>>())
         .and(warp::path::end())
+        .and(visitor())
         .and_then(post::show);
+    let post_show_by_slug = warp::get()
+        .and(warp::path("post"))
+        .and(warp::path("slug"))
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::path::param::<String>())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::path::end())
+        .and(visitor())
+        .and_then(post::show_by_slug);
+    let post_slug_page = warp::get()
+        .and(warp::path("p"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and_then(post::slug_page);
+    let series_list = warp::get()
+        .and(warp::path("series"))
+        .and(warp::path::end())
+        .and_then(series::list);
+    let series_save = warp::post()
+        .and(warp::path("series"))
+        .and(warp::path("save"))
+        .and(warp::path::end())
+        .and(auth())
+        .and(warp::body::json::<SeriesData>())
+        .and_then(series::save);
+    let series_show = warp::get()
+        .and(warp::path("series"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("accept"))
+        .and_then(series::show);
+    let comment_list = warp::get()
+        .and(warp::path("comment"))
+        .and(warp::path("list"))
+        .and(warp::path::param::<i64>())
+        .and(warp::path::end())
+        .and_then(comment::list);
+    let comment_add = warp::post()
+        .and(warp::path("comment"))
+        .and(warp::path("add"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::body::json::<CommentData>())
+        .and_then(comment::add);
+    let comment_moderate = warp::get()
+        .and(warp::path("comment"))
+        .and(warp::path("moderate"))
+        .and(warp::path::param::<i64>())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth())
+        .and_then(comment::moderate);
+    let comment_delete = warp::get()
+        .and(warp::path("comment"))
+        .and(warp::path("delete"))
+        .and(warp::path::param::<i64>())
+        .and(warp::path::end())
+        .and(auth())
+        .and_then(comment::delete);
+    let analytics_dashboard = warp::get()
+        .and(warp::path("analytics"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(management::analytics);
+    let management_markdown = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("markdown"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and_then(management::show_markdown_page);
+    let management_import = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and_then(management::show_import_page);
+    let management_import_markdown = warp::post()
+        .and(warp::path("management"))
+        .and(warp::path("import"))
+        .and(warp::path("markdown"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::content_length_limit(val::MAX_IMPORT_SIZE as u64))
+        .and(warp::body::bytes())
+        .and_then(management::import_markdown);
+    let management_import_wordpress = warp::post()
+        .and(warp::path("management"))
+        .and(warp::path("import"))
+        .and(warp::path("wordpress"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::content_length_limit(val::MAX_IMPORT_SIZE as u64))
+        .and(warp::body::bytes())
+        .and_then(management::import_wordpress);
+    let management_backup = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("backup"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and_then(management::show_backup_page);
+    let management_backup_download = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("backup"))
+        .and(warp::path("download"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and_then(management::download_backup);
+    let management_rerender = warp::post()
+        .and(warp::path("management"))
+        .and(warp::path("rerender"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(management::rerender);
+    let management_analytics = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("analytics"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(management::show_analytics_page);
+    let management_posts = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("posts"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(management::show_posts_page);
+    let management_posts_bulk = warp::post()
+        .and(warp::path("management"))
+        .and(warp::path("posts"))
+        .and(warp::path("bulk"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::body::json::<BulkRequest>())
+        .and_then(management::bulk_posts);
+    let management_comments = warp::get()
+        .and(warp::path("management"))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::cookie::optional(val::SESSION_ID_HEADER_NAME))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(management::show_comments_page);
     let upload_image = warp::post()
         .and(warp::path("image"))
         .and(warp::path("upload"))
@@ -332,6 +574,7 @@ pub fn blog_filter(
         .and(warp::path("export"))
         .and(warp::path::tail())
         .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
         .and(auth())
         .and_then(export::export_handler);
     let forgot_password = warp::get()
@@ -418,11 +661,43 @@ pub fn blog_filter(
         .or(post_list)
         .or(tags_all)
         .or(top_tags)
+        .or(tags_detail)
+        .or(tag_suggest)
+        .or(tag_update)
+        .or(tag_merge)
+        .or(tag_delete)
+        .or(management_tags)
         .or(post_list_by_tag)
+        .or(post_archive)
+        .or(post_list_by_month)
+        .or(post_related)
+        .or(post_featured)
+        .or(post_search)
         .or(post_new)
         .or(post_save)
         .or(post_delete)
         .or(post_show)
+        .or(post_show_by_slug)
+        .or(post_slug_page)
+        .or(series_list)
+        .or(series_save)
+        .or(series_show)
+        .or(comment_list)
+        .or(comment_add)
+        .or(comment_moderate)
+        .or(comment_delete)
+        .or(management_comments)
+        .or(management_posts)
+        .or(management_posts_bulk)
+        .or(analytics_dashboard)
+        .or(management_analytics)
+        .or(management_markdown)
+        .or(management_rerender)
+        .or(management_backup)
+        .or(management_backup_download)
+        .or(management_import)
+        .or(management_import_markdown)
+        .or(management_import_wordpress)
         .or(upload_image)
         .or(upload_title_image)
         .or(save_image)
@@ -118,8 +118,8 @@ pub fn get_verify_code(token: &str) -> Result<Vec<u8>> {
     Ok(numbers)
 }
 
-// pub fn check_verify_code(token: &str, code: &str) -> bool {
-pub fn check_verify_code(token: Option<String>, code: &str) -> Result<String> {
+// 验证码正确时才会从缓存里删掉，返回 token 和验证码是否正确
+fn match_verify_code(token: Option<String>, code: &str) -> Result<(String, bool)> {
     if token.is_none() {
         return Err(Error::InvalidSessionId.into());
     }
@@ -142,5 +142,18 @@ pub fn check_verify_code(token: Option<String>, code: &str) -> Result<String> {
     if valid_code {
         VERIFY_CODES.write().remove(&token);
     }
-    Ok(token)
+    Ok((token, valid_code))
+}
+
+// pub fn check_verify_code(token: &str, code: &str) -> bool {
+pub fn check_verify_code(token: Option<String>, code: &str) -> Result<String> {
+    match_verify_code(token, code).map(|(token, _)| token)
+}
+
+// 评论必须填对验证码
+pub fn require_verify_code(token: Option<String>, code: &str) -> Result<String> {
+    match match_verify_code(token, code)? {
+        (token, true) => Ok(token),
+        (_, false) => Err(Error::InvalidVerifyCode.into()),
+    }
 }
@@ -3,5 +3,6 @@ pub(crate) mod crypt;
 pub(crate) mod io;
 pub(crate) mod num;
 pub mod result;
+pub(crate) mod slug;
 pub(crate) mod snowflake;
 pub(crate) mod val;
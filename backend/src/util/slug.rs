@@ -0,0 +1,60 @@
+// 永久链接里的 slug 只保留小写字母、数字和连字符，中文等非 ASCII 字符先转写成拼音
+const MAX_SLUG_LENGTH: usize = 80;
+
+pub fn slugify(s: &str) -> String {
+    let transliterated = deunicode::deunicode(s);
+    let mut slug = String::with_capacity(transliterated.len());
+    let mut last_is_dash = true;
+    for c in transliterated.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+            last_is_dash = false;
+        } else if !last_is_dash {
+            slug.push('-');
+            last_is_dash = true;
+        }
+    }
+    if slug.len() > MAX_SLUG_LENGTH {
+        // 尽量在单词边界截断
+        let cut = match slug[..MAX_SLUG_LENGTH].rfind('-') {
+            Some(pos) if pos > 0 => pos,
+            _ => MAX_SLUG_LENGTH,
+        };
+        slug.truncate(cut);
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+pub fn fallback(post_id: i64) -> String {
+    format!("post-{}", post_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_title() {
+        assert_eq!(slugify("Hello, World!  Rust & Yew"), "hello-world-rust-yew");
+    }
+
+    #[test]
+    fn cjk_title() {
+        assert_eq!(slugify("你好 Rust"), "ni-hao-rust");
+    }
+
+    #[test]
+    fn punctuation_only() {
+        assert_eq!(slugify("？！…"), "");
+    }
+
+    #[test]
+    fn long_title_cut_at_word_boundary() {
+        let slug = slugify(&"word ".repeat(40));
+        assert!(slug.len() <= MAX_SLUG_LENGTH);
+        assert!(slug.ends_with("word"));
+    }
+}
@@ -9,3 +9,6 @@ pub const IMAGE_ROOT_PATH: &str = "/home/songday/website/blog/upload/image";
 // pub const BLOG_PAGE_SIZE: u8 = 20u8;
 // pub const I64SIZE: usize = std::mem::size_of::<i64>();
 pub(crate) const POST_DETAIL_RENDER_TEMPLATE: &'static str = "post_detail_render_template";
+pub(crate) const MARKDOWN_EXTENSIONS: &'static str = "markdown_extensions";
+pub(crate) const HIGHLIGHT_THEME: &'static str = "highlight_theme";
+pub(crate) const BACKUP_SCHEDULE: &'static str = "backup_schedule";
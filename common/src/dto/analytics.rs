@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DailyViews {
+    // YYYY-MM-DD
+    pub day: String,
+    pub views: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PostViews {
+    pub id: i64,
+    pub title: String,
+    pub slug: Option<String>,
+    pub views: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReferrerViews {
+    pub domain: String,
+    pub views: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AnalyticsDashboard {
+    pub days: u32,
+    pub total_views: u64,
+    // 按天升序，没有访问的日期也会补上 0
+    pub trend: Vec<DailyViews>,
+    pub top_posts: Vec<PostViews>,
+    pub referrers: Vec<ReferrerViews>,
+}
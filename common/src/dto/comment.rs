@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CommentData {
+    pub post_id: i64,
+    // 回复某条评论，只支持一层嵌套
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub website: String,
+    pub content: String,
+    // 匿名评论需要 /tool/verify-image 的验证码
+    #[serde(default)]
+    pub captcha: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CommentDetail {
+    pub id: i64,
+    pub post_id: i64,
+    pub parent_id: i64,
+    pub name: String,
+    pub website: String,
+    pub content: String,
+    pub created_at: u64,
+    pub replies: Vec<CommentDetail>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentStatus {
+    Pending,
+    Approved,
+    Spam,
+}
+
+// 提交评论后的结果，待审核的评论不会立刻显示
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CommentSubmitted {
+    pub status: CommentStatus,
+    pub comment: CommentDetail,
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Imported,
+    // 草稿、列表页等不需要导入的文件
+    Skipped,
+    Failed,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ImportedFile {
+    // 压缩包或目录里的相对路径，WordPress 导入时是原文地址
+    pub path: String,
+    pub status: ImportStatus,
+    pub post_id: Option<i64>,
+    pub title: String,
+    // 失败或跳过的原因，以及没能复制的图片等提示
+    pub messages: Vec<String>,
+    // 下面几项用于导入前预览
+    #[serde(default)]
+    pub created_at: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    // 预览时不会写入数据库，Imported 表示可以导入
+    #[serde(default)]
+    pub dry_run: bool,
+    pub files: Vec<ImportedFile>,
+}
+
+impl ImportReport {
+    pub fn push(&mut self, file: ImportedFile) {
+        match file.status {
+            ImportStatus::Imported => self.imported += 1,
+            ImportStatus::Skipped => self.skipped += 1,
+            ImportStatus::Failed => self.failed += 1,
+        }
+        self.files.push(file);
+    }
+}
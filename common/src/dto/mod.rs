@@ -4,9 +4,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::result::ErrorResponse;
 
+pub mod analytics;
+pub mod comment;
 pub mod git;
+pub mod import;
 pub mod management;
 pub mod post;
+pub mod series;
 pub mod tag;
 pub mod user;
 
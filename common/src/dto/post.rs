@@ -4,10 +4,45 @@
 //     str::FromStr,
 // };
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use super::series::PostSeries;
+
 // use crate::result::Error;
 
+// 以 JSON 保存在博客上的元数据，空字符串表示未设置
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PostMeta {
+    // SEO 描述
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub canonical_url: String,
+    #[serde(default)]
+    pub title_image_alt: String,
+    // 覆盖默认作者
+    #[serde(default)]
+    pub author: String,
+    // 例如 zh-CN、en
+    #[serde(default)]
+    pub language: String,
+    // 其它自定义字段，导出时会原样写进 front matter
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+// 目录中的一个标题，anchor 与渲染出来的标题 id 一致
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub title: String,
+    pub anchor: String,
+    #[serde(default)]
+    pub children: Vec<TocEntry>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct PostData {
     pub id: i64,
@@ -15,6 +50,38 @@ pub struct PostData {
     pub title_image: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    // 为空时根据标题自动生成
+    #[serde(default)]
+    pub slug: Option<String>,
+    // 系列名称，不存在时自动创建；为空表示不属于任何系列
+    #[serde(default)]
+    pub series: Option<String>,
+    // 为空时排在系列最后
+    #[serde(default)]
+    pub series_position: Option<u32>,
+    // 手动填写的摘要，为空时使用 <!--more--> 之前的内容或自动截取
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub meta: PostMeta,
+    // 置顶在首页第一页
+    #[serde(default)]
+    pub pinned: bool,
+    // 显示在首页的轮播图里
+    #[serde(default)]
+    pub featured: bool,
+    // 和哪篇博客互为翻译（那篇博客的 id），为空表示不属于任何翻译组，语言取 meta.language
+    #[serde(default)]
+    pub translation_of: Option<i64>,
+}
+
+// 同一翻译组里其它语言的版本
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Translation {
+    pub id: i64,
+    pub language: String,
+    pub title: String,
+    pub slug: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -27,6 +94,32 @@ pub struct PostDetail {
     pub created_at: u64,
     pub updated_at: Option<u64>,
     pub editable: bool,
+    #[serde(default)]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub series: Option<PostSeries>,
+    // 已审核通过的评论数
+    #[serde(default)]
+    pub comment_count: u32,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub meta: PostMeta,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub featured: bool,
+    // 保存时根据标题生成的目录
+    #[serde(default)]
+    pub toc: Vec<TocEntry>,
+    #[serde(default)]
+    pub translations: Vec<Translation>,
+    // 中日韩文字按字数，其它按单词数
+    #[serde(default)]
+    pub word_count: u32,
+    // 预计阅读时间，单位是分钟
+    #[serde(default)]
+    pub reading_time: u32,
 }
 
 impl PostDetail {
@@ -40,6 +133,17 @@ impl PostDetail {
             created_at: 0,
             updated_at: None,
             editable: false,
+            slug: None,
+            series: None,
+            comment_count: 0,
+            summary: None,
+            meta: PostMeta::default(),
+            pinned: false,
+            featured: false,
+            toc: Vec::new(),
+            translations: Vec::new(),
+            word_count: 0,
+            reading_time: 0,
         }
     }
 }
@@ -72,6 +176,85 @@ impl PostDetail {
 //     }
 // }
 
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RelatedPost {
+    pub id: i64,
+    pub title: String,
+    pub title_image: String,
+    pub slug: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RerenderFailure {
+    pub id: i64,
+    pub title: String,
+    pub error: String,
+}
+
+// 批量重新渲染的结果
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RerenderReport {
+    // 需要重新渲染的博客数
+    pub total: usize,
+    pub rendered: usize,
+    pub failures: Vec<RerenderFailure>,
+}
+
+// 后台博客列表的批量操作，状态指首页的置顶和轮播
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkAction {
+    AddTag {
+        tag: String,
+    },
+    RemoveTag {
+        tag: String,
+    },
+    Delete,
+    // 为空表示不修改
+    SetStatus {
+        pinned: Option<bool>,
+        featured: Option<bool>,
+    },
+    Export,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BulkRequest {
+    pub ids: Vec<i64>,
+    pub action: BulkAction,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BulkItemResult {
+    pub id: i64,
+    pub success: bool,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BulkReport {
+    pub results: Vec<BulkItemResult>,
+    // 导出时的下载地址
+    #[serde(default)]
+    pub download_uri: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArchiveMonth {
+    pub month: u32,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArchiveYear {
+    pub year: i32,
+    pub count: u32,
+    // 倒序，没有博客的月份不返回
+    pub months: Vec<ArchiveMonth>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UploadImage {
     pub relative_path: String,
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SeriesData {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SeriesPost {
+    pub id: i64,
+    pub title: String,
+    pub slug: Option<String>,
+    pub position: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SeriesDetail {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub posts: Vec<SeriesPost>,
+}
+
+// 博客详情里展示的系列信息，index 从 1 开始
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PostSeries {
+    pub name: String,
+    pub description: String,
+    pub position: u32,
+    pub index: u32,
+    pub total: u32,
+    pub prev: Option<SeriesPost>,
+    pub next: Option<SeriesPost>,
+}
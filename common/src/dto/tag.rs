@@ -6,3 +6,29 @@ pub struct TagUsageAmount {
     pub name: String,
     pub amount: u32,
 }
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TagDetail {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    // 形如 #3273dc，为空时前端随机取色
+    pub colour: String,
+    pub amount: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagData {
+    pub id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub colour: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagMerge {
+    pub from_id: i64,
+    pub into_id: i64,
+}
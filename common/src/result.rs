@@ -62,6 +62,12 @@ pub enum Error {
     SavePostIdDataByTagFailed,
     #[error("Tag not found")]
     TagNotFound,
+    #[error("标签已存在，请使用合并/Tag already exists, merge them instead")]
+    TagAlreadyExists,
+    #[error("Series not found")]
+    SeriesNotFound,
+    #[error("Comment not found")]
+    CommentNotFound,
 
     #[error("{0}")]
     BusinessException(String),
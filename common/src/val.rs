@@ -1,7 +1,9 @@
 pub const MAX_BLOG_UPLOAD_IMAGE_SIZE: usize = 5242880; //5mb
+pub const MAX_IMPORT_SIZE: usize = 268435456; //256mb
 pub const SESSION_ID_HEADER_NAME: &'static str = "X-SONGDAY-SESSION-ID";
 pub const USER_AUTH_MARK_HEADER: &'static str = "X-SONGDAY-USER-AUTHED";
 pub const POSTS_PAGE_SIZE: u8 = 8;
+pub const MAX_POSTS_PAGE_SIZE: u8 = 50;
 pub const DEFAULT_POST_TITLE: &'static str = "未命名/Untitled";
 pub const TAG_SIZES: [&'static str; 3] = [" is-normal", " is-medium", " is-large"];
 pub const TAG_COLORS: [&'static str; 8] = [
@@ -2,10 +2,12 @@ use weblog::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::page::post::SearchQuery;
 use crate::router::{switch, Route};
 
 pub enum Msg {
     Compose,
+    Search(String),
 }
 
 pub struct App;
@@ -38,11 +40,27 @@ impl Component for App {
                     }
                 });
             },
+            Msg::Search(keyword) => {
+                let navigator = ctx.link().navigator().unwrap();
+                navigator.push_with_query(&Route::Search, &SearchQuery { q: keyword, page: 1 });
+            },
         }
         false
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        let search = ctx.link().batch_callback(|e: KeyboardEvent| {
+            if e.key() != "Enter" {
+                return None;
+            }
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let keyword = input.value().trim().to_string();
+            if keyword.is_empty() {
+                None
+            } else {
+                Some(Msg::Search(keyword))
+            }
+        });
         html! {
             // https://cn.bing.com/search?form=MOZLBR&pc=MOZI&q=free+blog+logo
             // https://www.designevo.com/logo-maker/
@@ -68,6 +86,9 @@ impl Component for App {
                         <Link<Route> classes={"navbar-item"} to={Route::Tags}>
                             {"标签/Tags"}
                         </Link<Route>>
+                        <Link<Route> classes={"navbar-item"} to={Route::Archive}>
+                            {"归档/Archive"}
+                        </Link<Route>>
 
                       <div class="navbar-item has-dropdown is-hoverable">
                         <a class="navbar-link">
@@ -89,6 +110,14 @@ impl Component for App {
                       </div>
                     </div>
                     <div class="navbar-end">
+                      <div class="navbar-item">
+                        <div class="control has-icons-left">
+                            <input class="input" type="search" placeholder="搜索/Search" onkeyup={search}/>
+                            <span class="icon is-left">
+                                <i class="fas fa-search"></i>
+                            </span>
+                        </div>
+                      </div>
                       <div class="navbar-item">
                         <div class="buttons">
                             <button class="button" onclick={ctx.link().callback(|_| Msg::Compose)}>
@@ -0,0 +1,266 @@
+use blog_common::dto::comment::{CommentData, CommentDetail, CommentStatus, CommentSubmitted};
+use blog_common::dto::Response;
+use gloo::utils::document;
+use time::format_description;
+use time::OffsetDateTime;
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlInputElement, HtmlTextAreaElement, Node};
+use yew::prelude::*;
+
+use crate::i18n;
+
+#[wasm_bindgen(module = "/asset/show.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = userLanguage)]
+    fn user_language() -> String;
+}
+
+// 评论内容由后端渲染，已经过滤了原始 HTML
+fn show_comment_content(c: &str) -> Html {
+    let div: Element = document().create_element("div").unwrap();
+    div.set_inner_html(c);
+    div.set_class_name("content");
+    let node: Node = div.into();
+    Html::VRef(node)
+}
+
+fn format_time(timestamp: u64) -> String {
+    let datetime = OffsetDateTime::from_unix_timestamp(timestamp as i64).unwrap();
+    let format = format_description::parse("[year]-[month]-[day] [hour]:[minute]").unwrap();
+    datetime.format(&format).unwrap_or_default()
+}
+
+fn view_comment(comment: &CommentDetail, reply: Option<Callback<MouseEvent>>, reply_label: &str) -> Html {
+    let author = if comment.website.is_empty() {
+        html! { <strong>{ &comment.name }</strong> }
+    } else {
+        html! { <a href={comment.website.clone()} rel="nofollow ugc noopener" target="_blank"><strong>{ &comment.name }</strong></a> }
+    };
+    let reply_button = match reply {
+        Some(onclick) => html! { <a class="is-size-7" {onclick}>{ reply_label }</a> },
+        None => html! {},
+    };
+    html! {
+        <>
+            <p>{ author }{ " " }<small class="has-text-grey">{ format_time(comment.created_at) }</small></p>
+            { show_comment_content(&comment.content) }
+            { reply_button }
+        </>
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct CommentsProps {
+    pub post_id: u64,
+}
+
+#[function_component(Comments)]
+pub fn comments(CommentsProps { post_id }: &CommentsProps) -> Html {
+    let comments: UseStateHandle<Vec<CommentDetail>> = use_state(|| Vec::new());
+    let reply_to: UseStateHandle<Option<(i64, String)>> = use_state(|| None);
+    let notice: UseStateHandle<Option<String>> = use_state(|| None);
+    // 每次提交后刷新验证码图片
+    let captcha_seed = use_state(|| 0u32);
+    let reload = use_state(|| 0u32);
+    let name_ref = use_node_ref();
+    let email_ref = use_node_ref();
+    let website_ref = use_node_ref();
+    let content_ref = use_node_ref();
+    let captcha_ref = use_node_ref();
+    {
+        let comments = comments.clone();
+        use_effect_with_deps(
+            move |(post_id, _): &(u64, u32)| {
+                let uri = format!("/comment/list/{}", post_id);
+                let comments = comments.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<Vec<CommentDetail>> = reqwasm::http::Request::get(&uri)
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    comments.set(response.data.unwrap_or_default());
+                });
+                || ()
+            },
+            (*post_id, *reload),
+        );
+    }
+
+    let messages = i18n::get(
+        &user_language(),
+        vec![
+            "comments",
+            "comment_reply",
+            "comment_reply_to",
+            "comment_name",
+            "comment_email",
+            "comment_website",
+            "comment_content",
+            "comment_captcha",
+            "comment_submit",
+            "comment_pending",
+            "cancel",
+        ],
+    )
+    .unwrap();
+
+    let onsubmit = {
+        let post_id = *post_id;
+        let reply_to = reply_to.clone();
+        let notice = notice.clone();
+        let captcha_seed = captcha_seed.clone();
+        let reload = reload.clone();
+        let refs = (
+            name_ref.clone(),
+            email_ref.clone(),
+            website_ref.clone(),
+            content_ref.clone(),
+            captcha_ref.clone(),
+        );
+        let pending_message = messages.get("comment_pending").unwrap().to_string();
+        Callback::from(move |_: MouseEvent| {
+            let (name_ref, email_ref, website_ref, content_ref, captcha_ref) = refs.clone();
+            let input_value = |r: &NodeRef| r.cast::<HtmlInputElement>().map(|i| i.value()).unwrap_or_default();
+            let data = CommentData {
+                post_id: post_id as i64,
+                parent_id: reply_to.as_ref().map(|(id, _)| *id),
+                name: input_value(&name_ref),
+                email: input_value(&email_ref),
+                website: input_value(&website_ref),
+                content: content_ref
+                    .cast::<HtmlTextAreaElement>()
+                    .map(|t| t.value())
+                    .unwrap_or_default(),
+                captcha: input_value(&captcha_ref),
+            };
+            let payload = serde_json::to_string(&data).unwrap();
+            let reply_to = reply_to.clone();
+            let notice = notice.clone();
+            let captcha_seed = captcha_seed.clone();
+            let reload = reload.clone();
+            let pending_message = pending_message.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let response: Response<CommentSubmitted> = reqwasm::http::Request::post("/comment/add")
+                    .header("Content-Type", "application/json")
+                    .body(payload)
+                    .send()
+                    .await
+                    .unwrap()
+                    .json()
+                    .await
+                    .unwrap();
+                captcha_seed.set(*captcha_seed + 1);
+                if let Some(captcha) = captcha_ref.cast::<HtmlInputElement>() {
+                    captcha.set_value("");
+                }
+                match response.data {
+                    Some(submitted) => {
+                        if let Some(content) = content_ref.cast::<HtmlTextAreaElement>() {
+                            content.set_value("");
+                        }
+                        reply_to.set(None);
+                        if submitted.status == CommentStatus::Approved {
+                            notice.set(None);
+                            reload.set(*reload + 1);
+                        } else {
+                            notice.set(Some(pending_message));
+                        }
+                    },
+                    None => notice.set(response.error.map(|e| e.detail)),
+                }
+            });
+        })
+    };
+
+    let reply_label = messages.get("comment_reply").unwrap();
+    let threads = comments
+        .iter()
+        .map(|c| {
+            let reply = {
+                let reply_to = reply_to.clone();
+                let target = (c.id, c.name.clone());
+                Callback::from(move |_: MouseEvent| reply_to.set(Some(target.clone())))
+            };
+            let replies = c
+                .replies
+                .iter()
+                .map(|r| {
+                    html! {
+                        <article class="media">
+                            <div class="media-content">{ view_comment(r, None, reply_label) }</div>
+                        </article>
+                    }
+                })
+                .collect::<Html>();
+            html! {
+                <article class="media">
+                    <div class="media-content">
+                        { view_comment(c, Some(reply), reply_label) }
+                        { replies }
+                    </div>
+                </article>
+            }
+        })
+        .collect::<Html>();
+
+    let replying = match &*reply_to {
+        Some((_, name)) => {
+            let reply_to = reply_to.clone();
+            let cancel = Callback::from(move |_: MouseEvent| reply_to.set(None));
+            html! {
+                <div class="notification is-light">
+                    { messages.get("comment_reply_to").unwrap() }{ " " }<strong>{ name }</strong>{ " " }
+                    <a onclick={cancel}>{ messages.get("cancel").unwrap() }</a>
+                </div>
+            }
+        },
+        None => html! {},
+    };
+    let notice_box = match &*notice {
+        Some(n) => html! { <div class="notification is-warning is-light">{ n }</div> },
+        None => html! {},
+    };
+    let captcha_src = format!("/tool/verify-image?t={}", *captcha_seed);
+
+    html! {
+        <div class="container">
+            <h3 class="title is-4">{ messages.get("comments").unwrap() }{ format!(" ({})", comments.iter().map(|c| c.replies.len() + 1).sum::<usize>()) }</h3>
+            { threads }
+            <p>{" "}</p>
+            { replying }
+            { notice_box }
+            <div class="columns">
+                <div class="column field">
+                    <label class="label">{ messages.get("comment_name").unwrap() }</label>
+                    <input class="input" type="text" maxlength="64" ref={name_ref} />
+                </div>
+                <div class="column field">
+                    <label class="label">{ messages.get("comment_email").unwrap() }</label>
+                    <input class="input" type="email" maxlength="128" ref={email_ref} />
+                </div>
+                <div class="column field">
+                    <label class="label">{ messages.get("comment_website").unwrap() }</label>
+                    <input class="input" type="url" maxlength="256" placeholder="https://" ref={website_ref} />
+                </div>
+            </div>
+            <div class="field">
+                <label class="label">{ messages.get("comment_content").unwrap() }</label>
+                <textarea class="textarea" maxlength="4096" ref={content_ref}></textarea>
+            </div>
+            <div class="field is-grouped">
+                <div class="control">
+                    <input class="input" type="text" maxlength="4" placeholder={ messages.get("comment_captcha").unwrap().to_string() } ref={captcha_ref} />
+                </div>
+                <div class="control">
+                    <img src={captcha_src} />
+                </div>
+                <div class="control">
+                    <button class="button is-link" onclick={onsubmit}>{ messages.get("comment_submit").unwrap() }</button>
+                </div>
+            </div>
+        </div>
+    }
+}
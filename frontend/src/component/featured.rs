@@ -0,0 +1,108 @@
+use blog_common::dto::post::PostDetail;
+use blog_common::dto::Response;
+use gloo::timers::callback::Interval;
+use wasm_bindgen::prelude::*;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::i18n;
+use crate::router::{post_route, Route};
+
+// 轮播自动切换的间隔，单位毫秒
+const SLIDE_INTERVAL: u32 = 6000;
+
+#[wasm_bindgen(module = "/asset/show.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = userLanguage)]
+    fn user_language() -> String;
+}
+
+#[function_component(FeaturedCarousel)]
+pub fn featured_carousel() -> Html {
+    let posts: UseStateHandle<Vec<PostDetail>> = use_state(|| Vec::new());
+    let current = use_state(|| 0usize);
+    {
+        let posts = posts.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<Vec<PostDetail>> = reqwasm::http::Request::get("/post/featured")
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    posts.set(response.data.unwrap_or_default());
+                });
+                || ()
+            },
+            (),
+        );
+    }
+    let len = posts.len();
+    {
+        let current = current.clone();
+        // 依赖 current，手动切换后重新计时
+        use_effect_with_deps(
+            move |(len, index): &(usize, usize)| {
+                let (len, index) = (*len, *index);
+                let interval = if len > 1 {
+                    Some(Interval::new(SLIDE_INTERVAL, move || current.set((index + 1) % len)))
+                } else {
+                    None
+                };
+                move || drop(interval)
+            },
+            (len, *current),
+        );
+    }
+    if len == 0 {
+        return html! {};
+    }
+    let index = *current % len;
+    let post = &posts[index];
+    let prev = {
+        let current = current.clone();
+        Callback::from(move |_: MouseEvent| current.set((index + len - 1) % len))
+    };
+    let next = {
+        let current = current.clone();
+        Callback::from(move |_: MouseEvent| current.set((index + 1) % len))
+    };
+    let dots = (0..len)
+        .map(|i| {
+            let current = current.clone();
+            let class = if i == index { "button is-small is-white" } else { "button is-small is-white is-outlined" };
+            html! { <button class={class} onclick={Callback::from(move |_: MouseEvent| current.set(i))}>{ i + 1 }</button> }
+        })
+        .collect::<Html>();
+    let messages = i18n::get(&user_language(), vec!["featured_posts"]).unwrap();
+    html! {
+        <section class="hero is-medium is-dark has-background mb-5">
+            <img src={post.title_image.clone()} alt={post.meta.title_image_alt.clone()} class="hero-background is-transparent"/>
+            <div class="hero-body">
+                <p class="heading">{ messages.get("featured_posts").unwrap() }</p>
+                <Link<Route> classes={classes!("title", "is-2", "is-block")} to={post_route(post.id, &post.slug)}>
+                    { &post.title }
+                </Link<Route>>
+                if !post.content.is_empty() {
+                    <p class="subtitle">{ &post.content }</p>
+                }
+            </div>
+            if len > 1 {
+                <div class="hero-foot">
+                    <div class="buttons is-centered mb-3">
+                        <button class="button is-small is-white is-outlined" onclick={prev}>
+                            <span class="icon"><i class="fas fa-angle-left"></i></span>
+                        </button>
+                        { dots }
+                        <button class="button is-small is-white is-outlined" onclick={next}>
+                            <span class="icon"><i class="fas fa-angle-right"></i></span>
+                        </button>
+                    </div>
+                </div>
+            }
+        </section>
+    }
+}
@@ -1,6 +1,10 @@
+pub mod comments;
+pub mod featured;
 pub mod posts_list;
 pub mod unauthorized;
 
+pub use comments::Comments;
+pub use featured::FeaturedCarousel;
 pub use posts_list::PostsListComponent;
 pub use unauthorized::Unauthorized;
 
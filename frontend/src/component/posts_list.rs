@@ -1,4 +1,3 @@
-use std::rc::Rc;
 use std::vec::Vec;
 
 use blog_common::dto::post::PostDetail;
@@ -6,12 +5,13 @@ use blog_common::dto::{PaginationData, Response};
 use blog_common::val;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
+use web_sys::HtmlSelectElement;
 use weblog::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 use crate::i18n;
-use crate::router::Route;
+use crate::router::{post_route, Route};
 
 const POSTS_PAGE_SIZE: usize = val::POSTS_PAGE_SIZE as usize;
 
@@ -22,52 +22,92 @@ extern "C" {
 }
 
 fn view_posts(posts: Vec<&PostDetail>) -> Html {
+    let messages = i18n::get(&user_language(), vec!["min_read"]).unwrap();
     posts.iter().map(|&post| html! {
         <li class="list-item mb-5">
             <div class="card">
                 <div class="card-image">
                     <figure class="image is-2by1">
-                        <Link<Route> to={Route::ShowPost { id: post.id as u64 }}>
+                        <Link<Route> to={post_route(post.id, &post.slug)}>
                             <img alt={ post.title.clone() } src={post.title_image.clone()} loading="lazy" />
                         </Link<Route>>
                     </figure>
                 </div>
                 <div class="card-content">
-                    <Link<Route> classes={classes!("title", "is-block")} to={Route::ShowPost { id: post.id as u64 }}>
+                    <Link<Route> classes={classes!("title", "is-block")} to={post_route(post.id, &post.slug)}>
+                        if post.pinned {
+                            <span class="icon has-text-danger"><i class="fas fa-thumbtack"></i></span>
+                        }
                         { &post.title }
                     </Link<Route>>
+                    if !post.content.is_empty() {
+                        <p class="has-text-grey">{ &post.content }</p>
+                    }
+                    if post.reading_time > 0 {
+                        <span class="icon-text has-text-grey mr-3">
+                            <span class="icon"><i class="far fa-clock"></i></span>
+                            <span>{ format!("{} {}", post.reading_time, messages.get("min_read").unwrap()) }</span>
+                        </span>
+                    }
+                    if post.comment_count > 0 {
+                        <span class="icon-text has-text-grey">
+                            <span class="icon"><i class="far fa-comment"></i></span>
+                            <span>{ post.comment_count }</span>
+                        </span>
+                    }
                 </div>
             </div>
         </li>
     }).collect()
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 struct PaginationQuery {
-    // pagination_type
-    pub t: String,
-    // page num
-    pub pn: String,
-    // top id
-    pub tid: String,
-    // bottom id
-    pub bid: String,
+    #[serde(default = "first_page")]
+    pub page: u32,
+    // created|updated|title
+    #[serde(default = "default_sort")]
+    pub sort: String,
+    // asc|desc
+    #[serde(default = "default_order")]
+    pub order: String,
 }
 
-impl From<PaginationQuery> for PaginationState {
-    fn from(q: PaginationQuery) -> Self {
-        let page_num = q.pn.parse::<i64>().unwrap_or(0);
-        let pagination_type = match q.t.as_str() {
-            "p" => PaginationType::PREV(q.tid.parse::<i64>().unwrap_or(0)),
-            _ => PaginationType::NEXT(q.bid.parse::<i64>().unwrap_or(0)),
-        };
+fn first_page() -> u32 {
+    1
+}
+
+fn default_sort() -> String {
+    String::from("created")
+}
+
+fn default_order() -> String {
+    String::from("desc")
+}
+
+impl Default for PaginationQuery {
+    fn default() -> Self {
         Self {
-            page_num,
-            pagination_type,
+            page: first_page(),
+            sort: default_sort(),
+            order: default_order(),
         }
     }
 }
 
+// 页码太多时只显示首尾和当前页附近的几页，中间用省略号
+fn page_numbers(current: u32, total_pages: u32) -> Vec<Option<u32>> {
+    let mut pages = Vec::with_capacity(7);
+    for p in 1..=total_pages {
+        if p == 1 || p == total_pages || (p + 1 >= current && p <= current + 1) {
+            pages.push(Some(p));
+        } else if pages.last().map_or(false, |l: &Option<u32>| l.is_some()) {
+            pages.push(None);
+        }
+    }
+    pages
+}
+
 #[derive(PartialEq, Properties)]
 pub struct PostsListComponentProps {
     pub request_uri: String,
@@ -77,34 +117,29 @@ pub struct PostsListComponentProps {
 pub fn posts_list(PostsListComponentProps { request_uri }: &PostsListComponentProps) -> Html {
     let loc = use_location().unwrap();
     let nav = use_navigator().unwrap();
-    let pagination_state = match loc.query::<PaginationQuery>() {
-        Ok(q) => q.into(),
-        Err(e) => PaginationState::default(),
-    };
-    // let m = format!("{:?}", &pagination_state);
-    // console_log!(m);
+    let route = use_route::<Route>().unwrap_or(Route::ListPosts);
+    let query = loc.query::<PaginationQuery>().unwrap_or_default();
 
-    let pagination_state = use_reducer(|| pagination_state);
     let posts: UseStateHandle<Vec<PostDetail>> = use_state(|| Vec::with_capacity(0));
+    let total = use_state(|| 0u64);
     {
         let posts = posts.clone();
-        let mut uri = String::with_capacity(32);
-        uri.push_str(request_uri);
+        let total = total.clone();
+        let request_uri = request_uri.clone();
         use_effect_with_deps(
-            move |pagination_state| {
-                match pagination_state.pagination_type {
-                    PaginationType::PREV(id) => {
-                        uri.push_str("prev/");
-                        uri.push_str(id.to_string().as_str());
-                    },
-                    PaginationType::NEXT(bottom_id) => {
-                        uri.push_str("next/");
-                        uri.push_str(bottom_id.to_string().as_str());
-                    },
-                }
+            move |query: &PaginationQuery| {
+                // 只列出浏览器语言的博客，没有设置语言的博客都会列出
+                let uri = format!(
+                    "{}?page={}&size={}&sort={}&order={}&lang={}",
+                    request_uri,
+                    query.page,
+                    POSTS_PAGE_SIZE,
+                    query.sort,
+                    query.order,
+                    urlencoding::encode(&user_language())
+                );
                 console_log!("request uri=", &uri);
 
-                let posts = posts.clone();
                 wasm_bindgen_futures::spawn_local(async move {
                     let response: Response<PaginationData<Vec<PostDetail>>> = reqwasm::http::Request::get(uri.as_str())
                         .send()
@@ -113,11 +148,15 @@ pub fn posts_list(PostsListComponentProps { request_uri }: &PostsListComponentPr
                         .json()
                         .await
                         .unwrap();
-                    posts.set(response.data.unwrap().data);
+                    if let Some(d) = response.data {
+                        total.set(d.total);
+                        posts.set(d.data);
+                    }
+                    web_sys::window().unwrap().scroll_to_with_x_and_y(0.0, 0.0);
                 });
                 || ()
             },
-            pagination_state.clone(),
+            query.clone(),
         );
     }
     let posts = (*posts).clone();
@@ -125,8 +164,6 @@ pub fn posts_list(PostsListComponentProps { request_uri }: &PostsListComponentPr
     if len == 0 {
         return html! {};
     }
-    let top_id = posts[0].id;
-    let bottom_id = posts[len - 1].id;
     let row_num = len / 2 + 1;
     let mut left_column_data: Vec<&PostDetail> = Vec::with_capacity(row_num);
     let mut right_column_data: Vec<&PostDetail> = Vec::with_capacity(row_num);
@@ -140,47 +177,90 @@ pub fn posts_list(PostsListComponentProps { request_uri }: &PostsListComponentPr
             is_odd = true;
         }
     }
-    let prev_disabled = pagination_state.page_num < 2;
-    let next_disabled = len < POSTS_PAGE_SIZE;
+
+    let goto = {
+        let nav = nav.clone();
+        let route = route.clone();
+        move |q: PaginationQuery| {
+            let nav = nav.clone();
+            let route = route.clone();
+            Callback::from(move |_: MouseEvent| {
+                let _ = nav.push_with_query(&route, &q);
+            })
+        }
+    };
+    let page_of = |page: u32| PaginationQuery { page, ..query.clone() };
+    let total_pages = ((*total + POSTS_PAGE_SIZE as u64 - 1) / POSTS_PAGE_SIZE as u64).max(1) as u32;
+    let prev_disabled = query.page < 2;
+    let next_disabled = query.page >= total_pages;
     let prev = if prev_disabled {
         Callback::noop()
     } else {
-        let pagination_state = pagination_state.clone();
-        let nav = nav.clone();
-        Callback::from(move |_| {
-            nav.push_with_query(
-                &Route::ListPosts,
-                &PaginationQuery {
-                    t: "p".to_string(),
-                    pn: (pagination_state.page_num - 1).to_string(),
-                    tid: top_id.to_string(),
-                    bid: bottom_id.to_string(),
-                },
-            );
-            pagination_state.dispatch(PaginationType::PREV(top_id))
-        })
+        goto(page_of(query.page - 1))
     };
     let next = if next_disabled {
         Callback::noop()
     } else {
-        let pagination_state = pagination_state.clone();
-        Callback::from(move |_| {
-            nav.push_with_query(
-                &Route::ListPosts,
+        goto(page_of(query.page + 1))
+    };
+    let pages = page_numbers(query.page, total_pages)
+        .into_iter()
+        .map(|p| match p {
+            Some(p) => {
+                let class = if p == query.page {
+                    "pagination-link is-current"
+                } else {
+                    "pagination-link"
+                };
+                html! { <li><a class={class} onclick={goto(page_of(p))}>{ p }</a></li> }
+            },
+            None => html! { <li><span class="pagination-ellipsis">{ "…" }</span></li> },
+        })
+        .collect::<Html>();
+
+    let onsort = {
+        let route = route.clone();
+        Callback::from(move |e: Event| {
+            let select = e.target_unchecked_into::<HtmlSelectElement>();
+            let value = select.value();
+            let (sort, order) = value.split_once(':').unwrap_or(("created", "desc"));
+            let _ = nav.push_with_query(
+                &route,
                 &PaginationQuery {
-                    t: "n".to_string(),
-                    pn: (pagination_state.page_num + 1).to_string(),
-                    tid: top_id.to_string(),
-                    bid: bottom_id.to_string(),
+                    page: 1,
+                    sort: sort.to_string(),
+                    order: order.to_string(),
                 },
             );
-            pagination_state.dispatch(PaginationType::NEXT(bottom_id))
         })
     };
-    web_sys::window().unwrap().scroll_to_with_x_and_y(0.0, 0.0);
-    let messages = i18n::get(&user_language(), vec!["pp", "np"]).unwrap();
+    let current_sort = format!("{}:{}", query.sort, query.order);
+    let messages = i18n::get(
+        &user_language(),
+        vec!["pp", "np", "sort_newest", "sort_oldest", "sort_updated", "sort_title"],
+    )
+    .unwrap();
+    let sort_options = [
+        ("created:desc", "sort_newest"),
+        ("created:asc", "sort_oldest"),
+        ("updated:desc", "sort_updated"),
+        ("title:asc", "sort_title"),
+    ]
+    .iter()
+    .map(|(value, label)| {
+        html! { <option value={*value} selected={current_sort == *value}>{ messages.get(*label).unwrap() }</option> }
+    })
+    .collect::<Html>();
     html! {
         <>
+            <div class="level">
+                <div class="level-left"></div>
+                <div class="level-right">
+                    <div class="select is-small">
+                        <select onchange={onsort}>{ sort_options }</select>
+                    </div>
+                </div>
+            </div>
             <div class="columns">
                 <div class="column">
                     <ul class="list">
@@ -201,66 +281,9 @@ pub fn posts_list(PostsListComponentProps { request_uri }: &PostsListComponentPr
                     <a class="pagination-next" disabled={next_disabled} onclick={next}>
                         {messages.get("np").unwrap()}
                     </a>
+                    <ul class="pagination-list">{ pages }</ul>
                 </nav>
             </div>
         </>
     }
 }
-
-#[derive(Debug)]
-struct PaginationState {
-    page_num: i64,
-    pagination_type: PaginationType,
-}
-
-impl PartialEq for PaginationState {
-    fn eq(&self, other: &Self) -> bool {
-        // self.max_post_id == other.max_post_id && self.pagination_type.eq(&other.pagination_type)
-        let r = self.page_num == other.page_num && self.pagination_type.eq(&other.pagination_type);
-        console_log!("PaginationState_PartialEq", r);
-        r
-    }
-}
-
-impl Default for PaginationState {
-    fn default() -> Self {
-        Self {
-            page_num: 1,
-            pagination_type: PaginationType::NEXT(0),
-        }
-    }
-}
-
-impl Reducible for PaginationState {
-    type Action = PaginationType;
-
-    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
-        let page_num = match action {
-            PaginationType::NEXT(_bottom_id) => self.page_num + 1,
-            _ => self.page_num - 1,
-        };
-
-        Self {
-            page_num,
-            pagination_type: action,
-        }
-        .into()
-    }
-}
-
-#[derive(Clone, Copy, Debug)]
-pub enum PaginationType {
-    PREV(i64),
-    NEXT(i64),
-}
-
-impl PartialEq for PaginationType {
-    fn eq(&self, other: &Self) -> bool {
-        use PaginationType::*;
-        match (self, other) {
-            (&PREV(ref a), &PREV(ref b)) => a == b,
-            (&NEXT(ref a), &NEXT(ref b)) => a == b,
-            _ => false,
-        }
-    }
-}
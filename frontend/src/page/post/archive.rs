@@ -0,0 +1,147 @@
+use blog_common::dto::post::ArchiveYear;
+use blog_common::dto::Response;
+use wasm_bindgen::prelude::*;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::component::PostsListComponent;
+use crate::i18n;
+use crate::router::Route;
+
+#[wasm_bindgen(module = "/asset/show.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = userLanguage)]
+    fn user_language() -> String;
+}
+
+// 每个格子是一个月，颜色越深当月发的博客越多
+fn view_heatmap(years: &[ArchiveYear]) -> Html {
+    let max = years
+        .iter()
+        .flat_map(|y| y.months.iter().map(|m| m.count))
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let header = (1..=12u32).map(|m| html! { <th>{ m }</th> }).collect::<Html>();
+    let rows = years
+        .iter()
+        .map(|y| {
+            let cells = (1..=12u32)
+                .map(|month| {
+                    let count = y.months.iter().find(|m| m.month == month).map_or(0, |m| m.count);
+                    let title = format!("{}-{:02}: {}", y.year, month, count);
+                    if count == 0 {
+                        return html! { <td {title} style="background-color:#f5f5f5"></td> };
+                    }
+                    let alpha = 0.2 + 0.8 * count as f32 / max as f32;
+                    let style = format!("background-color:rgba(72,199,116,{:.2});padding:0", alpha);
+                    html! {
+                        <td {title} {style}>
+                            <Link<Route> classes={classes!("is-block")} to={Route::ArchiveMonth { year: y.year, month }}>{ "\u{00a0}" }</Link<Route>>
+                        </td>
+                    }
+                })
+                .collect::<Html>();
+            html! { <tr><th>{ y.year }</th>{ cells }</tr> }
+        })
+        .collect::<Html>();
+    html! {
+        <table class="table is-bordered is-narrow">
+            <thead><tr><th></th>{ header }</tr></thead>
+            <tbody>{ rows }</tbody>
+        </table>
+    }
+}
+
+fn view_tree(years: &[ArchiveYear], selected_year: Option<i32>) -> Html {
+    years
+        .iter()
+        .enumerate()
+        .map(|(idx, y)| {
+            let open = selected_year.map_or(idx == 0, |s| s == y.year);
+            let months = y
+                .months
+                .iter()
+                .map(|m| {
+                    html! {
+                        <li>
+                            <Link<Route> to={Route::ArchiveMonth { year: y.year, month: m.month }}>
+                                { format!("{}-{:02}", y.year, m.month) }
+                            </Link<Route>>
+                            { format!(" ({})", m.count) }
+                        </li>
+                    }
+                })
+                .collect::<Html>();
+            html! {
+                <details {open}>
+                    <summary><strong>{ y.year }</strong>{ format!(" ({})", y.count) }</summary>
+                    <ul>{ months }</ul>
+                </details>
+            }
+        })
+        .collect::<Html>()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Properties)]
+pub struct Props {
+    #[prop_or_default]
+    pub year: Option<i32>,
+    #[prop_or_default]
+    pub month: Option<u32>,
+}
+
+#[function_component(PostsArchive)]
+pub fn posts_archive(Props { year, month }: &Props) -> Html {
+    let years: UseStateHandle<Vec<ArchiveYear>> = use_state(|| Vec::new());
+    {
+        let years = years.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<Vec<ArchiveYear>> = reqwasm::http::Request::get("/post/archive")
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    years.set(response.data.unwrap_or_default());
+                });
+                || ()
+            },
+            (),
+        );
+    }
+
+    let messages = i18n::get(&user_language(), vec!["archive", "archive_activity"]).unwrap();
+    let title = match (year, month) {
+        (Some(y), Some(m)) => format!("{} {}-{:02}", messages.get("archive").unwrap(), y, m),
+        _ => messages.get("archive").unwrap().to_string(),
+    };
+    gloo::utils::document().set_title(&title);
+
+    let posts = match (year, month) {
+        (Some(y), Some(m)) => {
+            let request_uri = format!("/post/archive/{}/{}", y, m);
+            html! { <PostsListComponent {request_uri} /> }
+        },
+        _ => html! {},
+    };
+
+    html! {
+        <div class="container">
+            <h1 class="title is-1">{ title }</h1>
+            <div class="columns">
+                <div class="column is-3 content">
+                    { view_tree(&years, *year) }
+                </div>
+                <div class="column">
+                    <h4 class="subtitle">{ messages.get("archive_activity").unwrap() }</h4>
+                    <div class="table-container">{ view_heatmap(&years) }</div>
+                </div>
+            </div>
+            { posts }
+        </div>
+    }
+}
@@ -1,11 +1,11 @@
 
 use std::collections::HashMap;
 
-use blog_common::dto::post::{PostData, PostDetail};
+use blog_common::dto::post::{PostData, PostDetail, PostMeta};
 use blog_common::dto::Response;
 use gloo_file::callbacks::FileReader;
 use wasm_bindgen::prelude::*;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
 use weblog::*;
 use yew::events::InputEvent;
 use yew::prelude::*;
@@ -37,6 +37,187 @@ extern "C" {
     fn upload_title_image(event: Event, post_id: u64, files: Vec<web_sys::File>, payload_callback: JsValue);
 }
 
+#[derive(Clone, Debug, PartialEq, Properties)]
+struct TagInputProps {
+    placeholder: String,
+}
+
+// 单独做成组件，补全列表刷新时不会重新渲染整个编辑页
+#[function_component(TagInput)]
+fn tag_input(TagInputProps { placeholder }: &TagInputProps) -> Html {
+    let suggestions = use_state(|| Vec::<String>::new());
+    let oninput = {
+        let suggestions = suggestions.clone();
+        Callback::from(move |e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            let prefix = input.value().trim().to_string();
+            let suggestions = suggestions.clone();
+            if prefix.is_empty() {
+                suggestions.set(vec![]);
+                return;
+            }
+            wasm_bindgen_futures::spawn_local(async move {
+                let uri = format!("/tag/suggest?q={}", urlencoding::encode(&prefix));
+                let response: Response<Vec<String>> = reqwasm::http::Request::get(&uri)
+                    .send()
+                    .await
+                    .unwrap()
+                    .json()
+                    .await
+                    .unwrap();
+                suggestions.set(response.data.unwrap_or_default());
+            });
+        })
+    };
+    let options = suggestions
+        .iter()
+        .map(|name| html! { <option value={name.clone()} /> })
+        .collect::<Html>();
+    html! {
+        <>
+            <input maxlength="10" id="tagInput" class="input" type="text" list="tag-suggestions" placeholder={placeholder.clone()} onkeyup={input_tag} {oninput}/>
+            <datalist id="tag-suggestions">{ options }</datalist>
+        </>
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+struct MetaInputProps {
+    meta: PostMeta,
+    onchange: Callback<PostMeta>,
+}
+
+// 和 TagInput 一样有自己的状态，增删自定义字段时不影响编辑页的其它部分
+#[function_component(MetaInput)]
+fn meta_input(MetaInputProps { meta, onchange }: &MetaInputProps) -> Html {
+    let fields = use_state(|| meta.clone());
+    let extra = use_state(|| {
+        meta.extra
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<(String, String)>>()
+    });
+    let emit = {
+        let onchange = onchange.clone();
+        move |fields: &PostMeta, extra: &[(String, String)]| {
+            let mut meta = fields.clone();
+            meta.extra = extra.iter().filter(|(k, _)| !k.trim().is_empty()).cloned().collect();
+            onchange.emit(meta);
+        }
+    };
+    let field_oninput = |set: fn(&mut PostMeta, String)| {
+        let fields = fields.clone();
+        let extra = extra.clone();
+        let emit = emit.clone();
+        Callback::from(move |e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            let mut meta = (*fields).clone();
+            set(&mut meta, input.value());
+            emit(&meta, &extra);
+            fields.set(meta);
+        })
+    };
+    let change_extra = {
+        let fields = fields.clone();
+        let extra = extra.clone();
+        let emit = emit.clone();
+        move |change: &dyn Fn(&mut Vec<(String, String)>)| {
+            let mut rows = (*extra).clone();
+            change(&mut rows);
+            emit(&fields, &rows);
+            extra.set(rows);
+        }
+    };
+
+    let messages = i18n::get(
+        &user_language(),
+        vec![
+            "meta",
+            "meta_description",
+            "meta_canonical_url",
+            "meta_title_image_alt",
+            "meta_author",
+            "meta_language",
+            "meta_extra",
+            "meta_key",
+            "meta_value",
+            "meta_add_field",
+        ],
+    )
+    .unwrap();
+    let extra_rows = extra
+        .iter()
+        .enumerate()
+        .map(|(idx, (key, value))| {
+            let key_oninput = {
+                let change_extra = change_extra.clone();
+                Callback::from(move |e: InputEvent| {
+                    let v = e.target_unchecked_into::<HtmlInputElement>().value();
+                    change_extra(&|rows| rows[idx].0 = v.clone());
+                })
+            };
+            let value_oninput = {
+                let change_extra = change_extra.clone();
+                Callback::from(move |e: InputEvent| {
+                    let v = e.target_unchecked_into::<HtmlInputElement>().value();
+                    change_extra(&|rows| rows[idx].1 = v.clone());
+                })
+            };
+            let remove = {
+                let change_extra = change_extra.clone();
+                Callback::from(move |_: MouseEvent| {
+                    change_extra(&|rows| {
+                        rows.remove(idx);
+                    })
+                })
+            };
+            html! {
+                <div class="field has-addons">
+                    <div class="control">
+                        <input class="input" type="text" maxlength="64" placeholder={messages.get("meta_key").unwrap().to_string()} value={key.clone()} oninput={key_oninput}/>
+                    </div>
+                    <div class="control is-expanded">
+                        <input class="input" type="text" maxlength="1024" placeholder={messages.get("meta_value").unwrap().to_string()} value={value.clone()} oninput={value_oninput}/>
+                    </div>
+                    <div class="control">
+                        <button class="button" onclick={remove}><span class="icon"><i class="fas fa-times"></i></span></button>
+                    </div>
+                </div>
+            }
+        })
+        .collect::<Html>();
+    let add = Callback::from(move |_: MouseEvent| change_extra(&|rows| rows.push((String::new(), String::new()))));
+
+    html! {
+        <div class="field">
+            <label class="label">{ messages.get("meta").unwrap() }</label>
+            <div class="columns is-multiline">
+                <div class="column is-12">
+                    <input class="input" type="text" maxlength="1024" placeholder={messages.get("meta_description").unwrap().to_string()} value={fields.description.clone()} oninput={field_oninput(|m, v| m.description = v)}/>
+                </div>
+                <div class="column is-6">
+                    <input class="input" type="url" maxlength="1024" placeholder={messages.get("meta_canonical_url").unwrap().to_string()} value={fields.canonical_url.clone()} oninput={field_oninput(|m, v| m.canonical_url = v)}/>
+                </div>
+                <div class="column is-6">
+                    <input class="input" type="text" maxlength="1024" placeholder={messages.get("meta_title_image_alt").unwrap().to_string()} value={fields.title_image_alt.clone()} oninput={field_oninput(|m, v| m.title_image_alt = v)}/>
+                </div>
+                <div class="column is-6">
+                    <input class="input" type="text" maxlength="1024" placeholder={messages.get("meta_author").unwrap().to_string()} value={fields.author.clone()} oninput={field_oninput(|m, v| m.author = v)}/>
+                </div>
+                <div class="column is-6">
+                    <input class="input" type="text" maxlength="35" placeholder={messages.get("meta_language").unwrap().to_string()} value={fields.language.clone()} oninput={field_oninput(|m, v| m.language = v)}/>
+                </div>
+            </div>
+            <label class="label is-small">{ messages.get("meta_extra").unwrap() }</label>
+            { extra_rows }
+            <button class="button is-small" onclick={add}>
+                <span class="icon"><i class="fas fa-plus"></i></span>
+                <span>{ messages.get("meta_add_field").unwrap() }</span>
+            </button>
+        </div>
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Properties)]
 pub struct UpdatePostProps {
     onsubmit: Callback<FocusEvent>,
@@ -46,6 +227,18 @@ pub struct UpdatePostProps {
     post_id: u64,
     title_onchange: Callback<String>,
     title_image_onchange: Callback<String>,
+    slug_oninput: Callback<InputEvent>,
+    slug_onchange: Callback<String>,
+    series_oninput: Callback<InputEvent>,
+    series_position_oninput: Callback<InputEvent>,
+    series_onchange: Callback<(String, String)>,
+    summary_oninput: Callback<InputEvent>,
+    summary_onchange: Callback<String>,
+    translation_oninput: Callback<InputEvent>,
+    translation_onchange: Callback<String>,
+    meta_onchange: Callback<PostMeta>,
+    pinned_onchange: Callback<bool>,
+    featured_onchange: Callback<bool>,
 }
 
 #[function_component(UpdatePost)]
@@ -58,17 +251,39 @@ fn update_post(
         post_id,
         title_onchange,
         title_image_onchange,
+        slug_oninput,
+        slug_onchange,
+        series_oninput,
+        series_position_oninput,
+        series_onchange,
+        summary_oninput,
+        summary_onchange,
+        translation_oninput,
+        translation_onchange,
+        meta_onchange,
+        pinned_onchange,
+        featured_onchange,
     }: &UpdatePostProps,
 ) -> Html {
     let detail_url = format!("/post/show/{}?edit=true", post_id);
     console_log!("compose request post data");
     let post_detail = use_state(|| None::<PostDetail>);
+    let series_names = use_state(|| Vec::<String>::new());
     {
         let post_detail = post_detail.clone();
+        let series_names = series_names.clone();
         use_effect_with_deps(
             move |_| {
                 let post_detail = post_detail.clone();
                 wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<Vec<String>> = reqwasm::http::Request::get("/series")
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    series_names.set(response.data.unwrap_or_default());
                     let response: Response<PostDetail> = reqwasm::http::Request::get(&detail_url)
                         .send()
                         .await
@@ -97,6 +312,34 @@ fn update_post(
         };
     }
     title_onchange.emit(post_detail.title.clone());
+    slug_onchange.emit(post_detail.slug.clone().unwrap_or_default());
+    let (series_name, series_position) = match &post_detail.series {
+        Some(s) => (s.name.clone(), s.position.to_string()),
+        None => (String::new(), String::new()),
+    };
+    series_onchange.emit((series_name.clone(), series_position.clone()));
+    summary_onchange.emit(post_detail.summary.clone().unwrap_or_default());
+    // 关联到组里任意一篇就能留在这个组里
+    let translation_of = post_detail
+        .translations
+        .first()
+        .map(|t| t.id.to_string())
+        .unwrap_or_default();
+    translation_onchange.emit(translation_of.clone());
+    let translations = post_detail
+        .translations
+        .iter()
+        .map(|t| format!("{} {} ({})", t.language, t.title, t.id))
+        .collect::<Vec<String>>()
+        .join(", ");
+    meta_onchange.emit(post_detail.meta.clone());
+    pinned_onchange.emit(post_detail.pinned);
+    featured_onchange.emit(post_detail.featured);
+    let checked = |e: Event| e.target_unchecked_into::<HtmlInputElement>().checked();
+    let series_options = series_names
+        .iter()
+        .map(|name| html! { <option value={name.clone()} /> })
+        .collect::<Html>();
     if post_detail.title_image.len() > 0 {
         title_image_onchange.emit(post_detail.title_image.clone());
     }
@@ -114,7 +357,26 @@ fn update_post(
         show_origin_tags(origin_tags);
     }
 
-    let message_ids = vec!["ti", "upload_image", "or", "download_image", "title", "content"];
+    let message_ids = vec![
+        "ti",
+        "upload_image",
+        "or",
+        "download_image",
+        "title",
+        "slug",
+        "slug_help",
+        "series",
+        "series_help",
+        "series_position",
+        "series_position_help",
+        "summary",
+        "summary_help",
+        "translation_of",
+        "translation_of_help",
+        "pinned",
+        "featured",
+        "content",
+    ];
     let messages = i18n::get(&user_language(), message_ids).unwrap();
 
     gloo::utils::document().set_title(&post_detail.title);
@@ -161,6 +423,58 @@ fn update_post(
                         <input class="input" type="text" value={post_detail.title.clone()} oninput={oninput}/>
                     </div>
                 </div>
+                <div class="field">
+                    <label class="label">{ messages.get("slug").unwrap() }</label>
+                    <div class="control">
+                        <input class="input" type="text" value={post_detail.slug.clone().unwrap_or_default()} oninput={slug_oninput}/>
+                    </div>
+                    <p class="help">{ messages.get("slug_help").unwrap() }</p>
+                </div>
+                <div class="columns">
+                    <div class="column is-9 field">
+                        <label class="label">{ messages.get("series").unwrap() }</label>
+                        <div class="control">
+                            <input class="input" type="text" list="series-names" value={series_name} oninput={series_oninput}/>
+                            <datalist id="series-names">{ series_options }</datalist>
+                        </div>
+                        <p class="help">{ messages.get("series_help").unwrap() }</p>
+                    </div>
+                    <div class="column field">
+                        <label class="label">{ messages.get("series_position").unwrap() }</label>
+                        <div class="control">
+                            <input class="input" type="number" min="1" value={series_position} oninput={series_position_oninput}/>
+                        </div>
+                        <p class="help">{ messages.get("series_position_help").unwrap() }</p>
+                    </div>
+                </div>
+                <div class="field is-grouped">
+                    <label class="checkbox control">
+                        <input type="checkbox" checked={post_detail.pinned} onchange={pinned_onchange.reform(checked)}/>
+                        { " " }{ messages.get("pinned").unwrap() }
+                    </label>
+                    <label class="checkbox control">
+                        <input type="checkbox" checked={post_detail.featured} onchange={featured_onchange.reform(checked)}/>
+                        { " " }{ messages.get("featured").unwrap() }
+                    </label>
+                </div>
+                <div class="field">
+                    <label class="label">{ messages.get("summary").unwrap() }</label>
+                    <div class="control">
+                        <textarea class="textarea" rows="3" maxlength="1024" value={post_detail.summary.clone().unwrap_or_default()} oninput={summary_oninput}></textarea>
+                    </div>
+                    <p class="help">{ messages.get("summary_help").unwrap() }</p>
+                </div>
+                <MetaInput meta={post_detail.meta.clone()} onchange={meta_onchange.clone()} />
+                <div class="field">
+                    <label class="label">{ messages.get("translation_of").unwrap() }</label>
+                    <div class="control">
+                        <input class="input" type="text" inputmode="numeric" value={translation_of} oninput={translation_oninput}/>
+                    </div>
+                    <p class="help">{ messages.get("translation_of_help").unwrap() }</p>
+                    if !translations.is_empty() {
+                        <p class="help">{ translations }</p>
+                    }
+                </div>
                 <div class="field">
                     <label class="label">{ messages.get("content").unwrap() }</label>
                     <div id="post-content" style="display:none">{&post_detail.content}</div>
@@ -180,6 +494,14 @@ pub struct PostCompose {
     post_id: u64,
     title: String,
     title_image: String,
+    slug: String,
+    series: String,
+    series_position: String,
+    summary: String,
+    translation_of: String,
+    meta: PostMeta,
+    pinned: bool,
+    featured: bool,
     readers: HashMap<String, FileReader>,
 }
 
@@ -187,6 +509,14 @@ pub enum Msg {
     // RequestPostData(u64),
     Ignore,
     UpdateTitle(String),
+    UpdateSlug(String),
+    UpdateSeries(String),
+    UpdateSeriesPosition(String),
+    UpdateSummary(String),
+    UpdateTranslationOf(String),
+    UpdateMeta(PostMeta),
+    UpdatePinned(bool),
+    UpdateFeatured(bool),
     UpdatePost,
     LoadedBytes(String, Vec<u8>),
     Files(Event, Vec<web_sys::File>),
@@ -205,6 +535,14 @@ impl Component for PostCompose {
             post_id: ctx.props().post_id,
             title: String::new(),
             title_image: String::new(),
+            slug: String::new(),
+            series: String::new(),
+            series_position: String::new(),
+            summary: String::new(),
+            translation_of: String::new(),
+            meta: PostMeta::default(),
+            pinned: false,
+            featured: false,
             readers: HashMap::default(),
         }
     }
@@ -216,6 +554,14 @@ impl Component for PostCompose {
             },
             Msg::Ignore => {},
             Msg::UpdateTitle(s) => self.title = s,
+            Msg::UpdateSlug(s) => self.slug = s,
+            Msg::UpdateSeries(s) => self.series = s,
+            Msg::UpdateSeriesPosition(s) => self.series_position = s,
+            Msg::UpdateSummary(s) => self.summary = s,
+            Msg::UpdateTranslationOf(s) => self.translation_of = s,
+            Msg::UpdateMeta(m) => self.meta = m,
+            Msg::UpdatePinned(b) => self.pinned = b,
+            Msg::UpdateFeatured(b) => self.featured = b,
             Msg::UpdatePost => {
                 let selected_tags = get_added_tags();
                 let tags = if selected_tags.is_empty() {
@@ -229,13 +575,33 @@ impl Component for PostCompose {
                     title_image: self.title_image.clone(),
                     content: get_content(),
                     tags,
+                    slug: if self.slug.trim().is_empty() {
+                        None
+                    } else {
+                        Some(self.slug.trim().to_string())
+                    },
+                    series: if self.series.trim().is_empty() {
+                        None
+                    } else {
+                        Some(self.series.trim().to_string())
+                    },
+                    series_position: self.series_position.trim().parse::<u32>().ok(),
+                    summary: if self.summary.trim().is_empty() {
+                        None
+                    } else {
+                        Some(self.summary.trim().to_string())
+                    },
+                    meta: self.meta.clone(),
+                    pinned: self.pinned,
+                    featured: self.featured,
+                    translation_of: self.translation_of.trim().parse::<i64>().ok(),
                 };
                 console_log!(&post_data.content);
                 let navigator = ctx.link().navigator().unwrap();
                 let payload = serde_json::to_string(&post_data).unwrap();
                 let post_id = self.post_id;
                 wasm_bindgen_futures::spawn_local(async move {
-                    let _response: Response<PostDetail> = reqwasm::http::Request::post("/post/save")
+                    let response: Response<PostDetail> = reqwasm::http::Request::post("/post/save")
                         .header("Content-Type", "application/json")
                         .body(payload)
                         .send()
@@ -244,7 +610,11 @@ impl Component for PostCompose {
                         .json()
                         .await
                         .unwrap();
-                    navigator.push(&crate::router::Route::ShowPost { id: post_id });
+                    let route = match response.data {
+                        Some(detail) => crate::router::post_route(detail.id, &detail.slug),
+                        None => crate::router::Route::ShowPost { id: post_id },
+                    };
+                    navigator.push(&route);
                 });
 
                 // self.blog_params.tags = Some(get_selected_tags().iter().map(|e| e.as_string().unwrap()).collect());
@@ -337,6 +707,35 @@ impl Component for PostCompose {
 
         let title_onchange = ctx.link().callback(move |title: String| Msg::UpdateTitle(title));
         let title_image_onchange = ctx.link().callback(move |s: String| Msg::PayloadCallback(s));
+        let slug_onchange = ctx.link().callback(Msg::UpdateSlug);
+        let slug_oninput = ctx.link().callback(|e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            Msg::UpdateSlug(input.value())
+        });
+        let series_onchange = ctx.link().batch_callback(|(series, position): (String, String)| {
+            vec![Msg::UpdateSeries(series), Msg::UpdateSeriesPosition(position)]
+        });
+        let series_oninput = ctx.link().callback(|e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            Msg::UpdateSeries(input.value())
+        });
+        let series_position_oninput = ctx.link().callback(|e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            Msg::UpdateSeriesPosition(input.value())
+        });
+        let summary_onchange = ctx.link().callback(Msg::UpdateSummary);
+        let meta_onchange = ctx.link().callback(Msg::UpdateMeta);
+        let translation_onchange = ctx.link().callback(Msg::UpdateTranslationOf);
+        let translation_oninput = ctx.link().callback(|e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            Msg::UpdateTranslationOf(input.value())
+        });
+        let pinned_onchange = ctx.link().callback(Msg::UpdatePinned);
+        let featured_onchange = ctx.link().callback(Msg::UpdateFeatured);
+        let summary_oninput = ctx.link().callback(|e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlTextAreaElement>();
+            Msg::UpdateSummary(input.value())
+        });
 
         let onsubmit = ctx.link().callback(|ev: FocusEvent| {
             ev.prevent_default();
@@ -374,13 +773,16 @@ impl Component for PostCompose {
                 <p>{" "}</p>
                 <UpdatePost onsubmit={onsubmit} onchange={onchange} {download_image} oninput={oninput}
                     post_id={post_id as u64} title_onchange={title_onchange.clone()}
-                    title_image_onchange={title_image_onchange.clone()} />
+                    title_image_onchange={title_image_onchange.clone()} {slug_oninput} {slug_onchange}
+                    {series_oninput} {series_position_oninput} {series_onchange}
+                    {summary_oninput} {summary_onchange} {translation_oninput} {translation_onchange} {meta_onchange}
+                    {pinned_onchange} {featured_onchange} />
                 <div class="container" id="tagsContainer" style="display:none">
                     <p>{" "}</p>
                     <div class="field">
                         <label class="label">{ messages.get("labels").unwrap() }</label>
                         <div class="control">
-                            <input maxlength="10" id="tagInput" class="input" type="text" placeholder={ messages.get("add_label").unwrap().to_string() } onkeyup={input_tag}/>
+                            <TagInput placeholder={ messages.get("add_label").unwrap().to_string() } />
                         </div>
                         <br/>
                         <div id="tags" class="tags"></div>
@@ -1,4 +1,5 @@
-use blog_common::dto::post::PostDetail as PostDetailDto;
+use blog_common::dto::post::{PostDetail as PostDetailDto, RelatedPost, TocEntry, Translation};
+use blog_common::dto::series::PostSeries;
 use blog_common::dto::Response;
 use gloo::utils::document;
 use time::format_description;
@@ -8,8 +9,9 @@ use web_sys::{Element, Node};
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::component::Comments;
 use crate::i18n;
-use crate::router::Route;
+use crate::router::{post_route, Route};
 
 #[wasm_bindgen(module = "/asset/show.js")]
 extern "C" {
@@ -53,19 +55,118 @@ fn show_tags(post: &mut PostDetailDto) -> Html {
     }
 }
 
+fn show_series(series: &Option<PostSeries>) -> Html {
+    let series = match series {
+        Some(s) => s,
+        None => return html! {},
+    };
+    let messages = i18n::get(&user_language(), vec!["series_part", "series_prev", "series_next"]).unwrap();
+    let series_route = Route::ListPostsBySeries {
+        name: urlencoding::encode(&series.name).into_owned(),
+    };
+    let prev = match &series.prev {
+        Some(p) => html! {
+            <Link<Route> classes={classes!("pagination-previous")} to={post_route(p.id, &p.slug)}>
+                { messages.get("series_prev").unwrap() }{ ": " }{ &p.title }
+            </Link<Route>>
+        },
+        None => html! {},
+    };
+    let next = match &series.next {
+        Some(p) => html! {
+            <Link<Route> classes={classes!("pagination-next")} to={post_route(p.id, &p.slug)}>
+                { messages.get("series_next").unwrap() }{ ": " }{ &p.title }
+            </Link<Route>>
+        },
+        None => html! {},
+    };
+    html! {
+        <div class="notification is-info is-light">
+            <p>
+                { messages.get("series_part").unwrap() }{ " " }
+                <Link<Route> to={series_route}>{ &series.name }</Link<Route>>
+                { format!(" ({}/{})", series.index, series.total) }
+            </p>
+            <nav class="pagination" role="navigation" aria-label="series">
+                { prev }
+                { next }
+            </nav>
+        </div>
+    }
+}
+
+fn show_translations(translations: &[Translation]) -> Html {
+    if translations.is_empty() {
+        return html! {};
+    }
+    let messages = i18n::get(&user_language(), vec!["translations"]).unwrap();
+    let links = translations
+        .iter()
+        .map(|t| {
+            html! {
+                <Link<Route> classes={classes!("tag", "is-link", "is-light")} to={post_route(t.id, &t.slug)}>
+                    <span lang={t.language.clone()}>{ format!("{} · {}", t.language, t.title) }</span>
+                </Link<Route>>
+            }
+        })
+        .collect::<Html>();
+    html! {
+        <div class="tags">
+            <span class="tag">{ messages.get("translations").unwrap() }</span>
+            { links }
+        </div>
+    }
+}
+
+fn show_toc_entries(entries: &[TocEntry]) -> Html {
+    entries
+        .iter()
+        .map(|e| {
+            html! {
+                <li>
+                    <a href={format!("#{}", e.anchor)}>{ &e.title }</a>
+                    if !e.children.is_empty() {
+                        <ul>{ show_toc_entries(&e.children) }</ul>
+                    }
+                </li>
+            }
+        })
+        .collect()
+}
+
+fn show_toc(toc: &[TocEntry]) -> Html {
+    let messages = i18n::get(&user_language(), vec!["toc"]).unwrap();
+    html! {
+        <aside class="menu" style="position:sticky;top:1.5rem">
+            <p class="menu-label">{ messages.get("toc").unwrap() }</p>
+            <ul class="menu-list">{ show_toc_entries(toc) }</ul>
+        </aside>
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Properties)]
 pub struct ShowDetailProps {
     pub post_id: u64,
+    pub slug: String,
+    pub onload: Callback<u64>,
 }
 
 #[function_component(ShowDetail)]
-fn app(ShowDetailProps { post_id }: &ShowDetailProps) -> Html {
-    let detail_url = format!("/post/show/{}", post_id);
+fn app(ShowDetailProps { post_id, slug, onload }: &ShowDetailProps) -> Html {
+    // 把外部来源带给后端做统计，后端只保留域名
+    let referrer = urlencoding::encode(&document().referrer()).into_owned();
+    let detail_url = if slug.is_empty() {
+        format!("/post/show/{}?ref={}", post_id, referrer)
+    } else {
+        format!("/post/slug/{}?ref={}", slug, referrer)
+    };
     let post_detail = use_state(|| PostDetailDto::default());
     {
         let post_detail = post_detail.clone();
+        let onload = onload.clone();
         use_effect_with_deps(
-            move |_| {
+            move |detail_url: &String| {
+                let detail_url = detail_url.clone();
                 let post_detail = post_detail.clone();
                 wasm_bindgen_futures::spawn_local(async move {
                     let response: Response<PostDetailDto> = reqwasm::http::Request::get(&detail_url)
@@ -75,11 +176,13 @@ fn app(ShowDetailProps { post_id }: &ShowDetailProps) -> Html {
                         .json()
                         .await
                         .unwrap();
-                    post_detail.set(response.data.unwrap());
+                    let detail = response.data.unwrap();
+                    onload.emit(detail.id as u64);
+                    post_detail.set(detail);
                 });
                 || ()
             },
-            (),
+            detail_url,
         );
     }
     let mut post = (*post_detail).clone();
@@ -88,10 +191,11 @@ fn app(ShowDetailProps { post_id }: &ShowDetailProps) -> Html {
     let format = format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second]").unwrap();
     let post_time = datetime.format(&format).expect("Failed to format the date");
     gloo::utils::document().set_title(&post.title);
+    let messages = i18n::get(&user_language(), vec!["min_read"]).unwrap();
     html! {
         <>
             <section class="hero is-large is-light has-background">
-                <img src={ title_image } class="hero-background is-transparent"/>
+                <img src={ title_image } alt={ post.meta.title_image_alt.clone() } class="hero-background is-transparent"/>
                 <div class="hero-body">
                     <div class="container">
                         <p class="title is-1">
@@ -100,58 +204,147 @@ fn app(ShowDetailProps { post_id }: &ShowDetailProps) -> Html {
                         </p>
                         <p class="subtitle is-3">
                             { &post_time }
+                            if !post.meta.author.is_empty() {
+                                { " · " }{ &post.meta.author }
+                            }
+                            if post.reading_time > 0 {
+                                { format!(" · {} {}", post.reading_time, messages.get("min_read").unwrap()) }
+                            }
                         </p>
                         {show_tags(&mut post)}
                     </div>
                 </div>
             </section>
             <div class="section container">
-                <article class="media block box my-6">
-                    <div class="media-content">
-                        { show_content(&post.content) }
-                        // <div class="content">
-                            // <p class="is-family-secondary">
-                            //     { show_content(&post.content) }
-                            // </p>
-                        // </div>
+                { show_translations(&post.translations) }
+                { show_series(&post.series) }
+                <div class="columns">
+                    <div class="column">
+                        <article class="media block box my-6" lang={ if post.meta.language.is_empty() { None } else { Some(post.meta.language.clone()) } }>
+                            <div class="media-content">
+                                { show_content(&post.content) }
+                                // <div class="content">
+                                    // <p class="is-family-secondary">
+                                    //     { show_content(&post.content) }
+                                    // </p>
+                                // </div>
+                            </div>
+                        </article>
                     </div>
-                </article>
+                    if !post.toc.is_empty() {
+                        <div class="column is-3 is-hidden-mobile my-6">
+                            { show_toc(&post.toc) }
+                        </div>
+                    }
+                </div>
             </div>
         </>
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Properties)]
+struct RelatedPostsProps {
+    post_id: u64,
+}
+
+#[function_component(RelatedPosts)]
+fn related_posts(RelatedPostsProps { post_id }: &RelatedPostsProps) -> Html {
+    let posts: UseStateHandle<Vec<RelatedPost>> = use_state(|| Vec::new());
+    {
+        let posts = posts.clone();
+        use_effect_with_deps(
+            move |post_id: &u64| {
+                let uri = format!("/post/related/{}", post_id);
+                wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<Vec<RelatedPost>> = reqwasm::http::Request::get(&uri)
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    posts.set(response.data.unwrap_or_default());
+                });
+                || ()
+            },
+            *post_id,
+        );
+    }
+    if posts.is_empty() {
+        return html! {};
+    }
+    let messages = i18n::get(&user_language(), vec!["related_posts"]).unwrap();
+    let items = posts
+        .iter()
+        .map(|p| {
+            html! {
+                <li>
+                    <Link<Route> to={post_route(p.id, &p.slug)}>{ &p.title }</Link<Route>>
+                </li>
+            }
+        })
+        .collect::<Html>();
+    html! {
+        <div class="container content">
+            <h4>{ messages.get("related_posts").unwrap() }</h4>
+            <ul>{ items }</ul>
+        </div>
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Properties)]
 pub struct Props {
     pub post_id: u64,
+    // 通过永久链接访问时，post_id 要等加载完才知道
+    #[prop_or_default]
+    pub slug: String,
 }
 
 pub struct PostDetail {
     pub post_id: u64,
+    pub slug: String,
+}
+
+pub enum Msg {
+    Loaded(u64),
 }
 
 impl Component for PostDetail {
-    type Message = ();
+    type Message = Msg;
     type Properties = Props;
 
     fn create(ctx: &Context<Self>) -> Self {
         Self {
             post_id: ctx.props().post_id,
+            slug: ctx.props().slug.clone(),
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Loaded(id) => {
+                let changed = self.post_id != id;
+                self.post_id = id;
+                changed
+            },
         }
     }
 
     fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
-        let changed = self.post_id != ctx.props().post_id;
+        let props = ctx.props();
+        let changed = self.slug.ne(&props.slug) || (props.slug.is_empty() && self.post_id != props.post_id);
         if changed {
             weblog::console_log!("changed to load");
-            self.post_id = ctx.props().post_id;
+            self.post_id = props.post_id;
+            self.slug = props.slug.clone();
         }
         changed
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         // weblog::console_log!("show_detail");
-        let Self { post_id } = self;
+        let Self { post_id, slug } = self;
+        let onload = ctx.link().callback(Msg::Loaded);
         let mut delete_post_uri = String::with_capacity(32);
         delete_post_uri.push_str("/post/delete/");
         delete_post_uri.push_str(post_id.to_string().as_str());
@@ -169,9 +362,21 @@ impl Component for PostDetail {
         .unwrap();
 
         web_sys::window().unwrap().scroll_to_with_x_and_y(0.0, 0.0);
+        // 通过 slug 访问时，要等博客加载完才知道 id
+        let comments = if *post_id > 0 {
+            html! {
+                <>
+                    <RelatedPosts post_id={*post_id} />
+                    <p>{" "}</p>
+                    <Comments post_id={*post_id} />
+                </>
+            }
+        } else {
+            html! {}
+        };
         html! {
             <>
-                <ShowDetail post_id={*post_id} />
+                <ShowDetail post_id={*post_id} slug={slug.clone()} {onload} />
                 <div class="container">
                     <div class="buttons are-small">
                         <button class="button" onclick={go_back}>
@@ -202,6 +407,8 @@ impl Component for PostDetail {
                         </div>
                     </div>
                 </div>
+                <p>{" "}</p>
+                { comments }
             </>
         }
     }
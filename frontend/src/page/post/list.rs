@@ -1,6 +1,6 @@
 use yew::prelude::*;
 
-use crate::component::PostsListComponent;
+use crate::component::{FeaturedCarousel, PostsListComponent};
 
 pub struct PostsList {}
 
@@ -23,7 +23,8 @@ impl Component for PostsList {
                         <h2 class="subtitle">{ "All of your quality writing in one place" }</h2>
                     </div>
                 </div>
-                <PostsListComponent request_uri={"/post/list/".to_string()} />
+                <FeaturedCarousel />
+                <PostsListComponent request_uri={"/post/list".to_string()} />
             </>
         }
     }
@@ -0,0 +1,85 @@
+use blog_common::dto::series::SeriesDetail;
+use blog_common::dto::Response;
+use wasm_bindgen::prelude::*;
+use weblog::*;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::i18n;
+use crate::router::{post_route, Route};
+
+#[wasm_bindgen(module = "/asset/show.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = userLanguage)]
+    fn user_language() -> String;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Properties)]
+pub struct Props {
+    pub name: String,
+}
+
+#[function_component(PostsListBySeries)]
+pub fn posts_list_by_series(Props { name }: &Props) -> Html {
+    let series: UseStateHandle<Option<SeriesDetail>> = use_state(|| None);
+    {
+        let series = series.clone();
+        use_effect_with_deps(
+            move |name: &String| {
+                let uri = format!("/series/{}", name);
+                console_log!("request uri=", &uri);
+                let series = series.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<SeriesDetail> = reqwasm::http::Request::get(&uri)
+                        .header("Accept", "application/json")
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    series.set(response.data);
+                });
+                || ()
+            },
+            name.clone(),
+        );
+    }
+
+    let decoded_name = urlencoding::decode(name).unwrap();
+    gloo::utils::document().set_title(&decoded_name);
+    let messages = i18n::get(&user_language(), vec!["series_posts"]).unwrap();
+
+    let body = match (*series).as_ref() {
+        None => html! {},
+        Some(s) => {
+            let posts = s
+                .posts
+                .iter()
+                .map(|p| {
+                    html! {
+                        <li>
+                            <Link<Route> to={post_route(p.id, &p.slug)}>{ &p.title }</Link<Route>>
+                        </li>
+                    }
+                })
+                .collect::<Html>();
+            html! {
+                <>
+                    <h2 class="subtitle">{ &s.description }</h2>
+                    <div class="content">
+                        <h4>{ messages.get("series_posts").unwrap() }</h4>
+                        <ol>{ posts }</ol>
+                    </div>
+                </>
+            }
+        },
+    };
+
+    html! {
+        <div class="container">
+            <h1 class="title is-1">{ decoded_name }</h1>
+            { body }
+        </div>
+    }
+}
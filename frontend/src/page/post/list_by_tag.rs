@@ -45,7 +45,6 @@ impl Component for PostsListByTag {
         let mut request_uri = String::with_capacity(32);
         request_uri.push_str("/post/tag/");
         request_uri.push_str(tag_name);
-        request_uri.push_str("/");
 
         let decoded_tag_name = urlencoding::decode(tag_name).unwrap();
 
@@ -1,9 +1,15 @@
+mod archive;
 mod compose;
 mod detail;
 mod list;
+mod list_by_series;
 mod list_by_tag;
+mod search;
 
+pub use archive::PostsArchive;
 pub use compose::PostCompose;
 pub use detail::PostDetail;
 pub use list::PostsList;
+pub use list_by_series::PostsListBySeries;
 pub use list_by_tag::PostsListByTag;
+pub use search::{PostsSearch, SearchQuery};
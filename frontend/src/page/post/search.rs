@@ -0,0 +1,148 @@
+use blog_common::dto::post::PostDetail;
+use blog_common::dto::{PaginationData, Response};
+use blog_common::val;
+use gloo::utils::document;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, Node};
+use weblog::*;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::i18n;
+use crate::router::{post_route, Route};
+
+#[wasm_bindgen(module = "/asset/show.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = userLanguage)]
+    fn user_language() -> String;
+}
+
+#[derive(Clone, Default, Deserialize, PartialEq, Serialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub page: u32,
+}
+
+// 后端已经转义过，这里只会多出 <mark> 高亮标签
+fn show_snippet(c: &str) -> Html {
+    let div: Element = document().create_element("div").unwrap();
+    div.set_inner_html(c);
+    div.set_class_name("content");
+    let node: Node = div.into();
+    Html::VRef(node)
+}
+
+fn view_results(posts: &Vec<PostDetail>) -> Html {
+    posts
+        .iter()
+        .map(|post| {
+            let tags = post
+                .tags
+                .as_ref()
+                .map(|tags| {
+                    tags.iter()
+                        .map(|t| html! {<span class="tag is-info is-light">{t}</span>})
+                        .collect::<Html>()
+                })
+                .unwrap_or_else(|| html! {});
+            html! {
+                <li class="list-item mb-5">
+                    <div class="box">
+                        <Link<Route> classes={classes!("title", "is-4", "is-block")} to={post_route(post.id, &post.slug)}>
+                            { &post.title }
+                        </Link<Route>>
+                        { show_snippet(&post.content) }
+                        <div class="tags">{tags}</div>
+                    </div>
+                </li>
+            }
+        })
+        .collect()
+}
+
+#[function_component(PostsSearch)]
+pub fn posts_search() -> Html {
+    let loc = use_location().unwrap();
+    let nav = use_navigator().unwrap();
+    let query = loc.query::<SearchQuery>().unwrap_or_default();
+    let page = if query.page < 1 { 1 } else { query.page };
+    let result: UseStateHandle<Option<PaginationData<Vec<PostDetail>>>> = use_state(|| None);
+    {
+        let result = result.clone();
+        use_effect_with_deps(
+            move |(keyword, page): &(String, u32)| {
+                let uri = format!("/post/search?q={}&page={}", urlencoding::encode(keyword), page);
+                console_log!("request uri=", &uri);
+                let result = result.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let response: Response<PaginationData<Vec<PostDetail>>> = reqwasm::http::Request::get(&uri)
+                        .send()
+                        .await
+                        .unwrap()
+                        .json()
+                        .await
+                        .unwrap();
+                    result.set(response.data);
+                });
+                || ()
+            },
+            (query.q.clone(), page),
+        );
+    }
+
+    let messages = i18n::get(&user_language(), vec!["search_results", "no_result", "pp", "np"]).unwrap();
+    document().set_title(&query.q);
+
+    let body = match (*result).as_ref() {
+        None => html! {},
+        Some(r) if r.data.is_empty() => html! {
+            <div class="notification">{ messages.get("no_result").unwrap() }</div>
+        },
+        Some(r) => {
+            let page_size = val::POSTS_PAGE_SIZE as u64;
+            let prev_disabled = page < 2;
+            let next_disabled = page as u64 * page_size >= r.total;
+            let goto = |target: u32| {
+                let nav = nav.clone();
+                let q = query.q.clone();
+                Callback::from(move |_: MouseEvent| {
+                    nav.push_with_query(
+                        &Route::Search,
+                        &SearchQuery {
+                            q: q.clone(),
+                            page: target,
+                        },
+                    );
+                })
+            };
+            let prev = if prev_disabled { Callback::noop() } else { goto(page - 1) };
+            let next = if next_disabled { Callback::noop() } else { goto(page + 1) };
+            html! {
+                <>
+                    <ul class="list">
+                        { view_results(&r.data) }
+                    </ul>
+                    <nav class="pagination is-right" role="navigation" aria-label="pagination">
+                        <a class="pagination-previous" disabled={prev_disabled} onclick={prev}>
+                            {messages.get("pp").unwrap()}
+                        </a>
+                        <a class="pagination-next" disabled={next_disabled} onclick={next}>
+                            {messages.get("np").unwrap()}
+                        </a>
+                    </nav>
+                </>
+            }
+        },
+    };
+
+    html! {
+        <div class="container">
+            <h1 class="title is-1">{ messages.get("search_results").unwrap() }</h1>
+            <h2 class="subtitle">{ &query.q }</h2>
+            { body }
+        </div>
+    }
+}
@@ -2,6 +2,7 @@ use rand::Rng;
 use std::vec::Vec;
 
 use blog_common::dto::post::PostDetail;
+use blog_common::dto::tag::TagDetail;
 use blog_common::dto::{PaginationData, Response};
 use blog_common::val;
 use weblog::*;
@@ -12,17 +13,17 @@ use crate::router::Route;
 
 #[function_component(TagsListComponent)]
 fn tags_list() -> Html {
-    let tags: UseStateHandle<Vec<String>> = use_state(|| Vec::with_capacity(0));
+    let tags: UseStateHandle<Vec<TagDetail>> = use_state(|| Vec::with_capacity(0));
     {
         let tags = tags.clone();
         let mut uri = String::with_capacity(32);
-        uri.push_str("/tags/all");
+        uri.push_str("/tags/detail");
         use_effect_with_deps(
             move |_| {
                 let tags = tags.clone();
                 console_log!("request uri");
                 wasm_bindgen_futures::spawn_local(async move {
-                    let response: Response<Vec<String>> = reqwasm::http::Request::get(uri.as_str())
+                    let response: Response<Vec<TagDetail>> = reqwasm::http::Request::get(uri.as_str())
                         .send()
                         .await
                         .unwrap()
@@ -50,13 +51,19 @@ fn tags_list() -> Html {
             let mut roll: usize = rng.gen_range(0..val::TAG_SIZES.len());
             classes.push_str("tag is-light");
             classes.push_str(val::TAG_SIZES[roll]);
-            // roll = fastrand::usize(..val::TAG_COLORS.len());
-            roll = rng.gen_range(0..val::TAG_COLORS.len());
-            classes.push_str(val::TAG_COLORS[roll]);
+            // 设置了颜色的标签用自己的颜色
+            let style = if t.colour.is_empty() {
+                // roll = fastrand::usize(..val::TAG_COLORS.len());
+                roll = rng.gen_range(0..val::TAG_COLORS.len());
+                classes.push_str(val::TAG_COLORS[roll]);
+                String::new()
+            } else {
+                format!("border: 1px solid {}", t.colour)
+            };
             let html = html! {
-                <span class={classes!(&classes)}>
-                    <Link<Route> to={Route::ListPostsByTag { tag_name: String::from(t) }}>
-                        { t }
+                <span class={classes!(&classes)} {style} title={t.description.clone()}>
+                    <Link<Route> to={Route::ListPostsByTag { tag_name: t.name.clone() }}>
+                        { &t.name }
                     </Link<Route>>
                 </span>
             };
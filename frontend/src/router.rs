@@ -1,19 +1,31 @@
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-use crate::page::post::{PostCompose, PostDetail, PostsList, PostsListByTag};
+use crate::page::post::{
+    PostCompose, PostDetail, PostsArchive, PostsList, PostsListBySeries, PostsListByTag, PostsSearch,
+};
 use crate::page::tag::TagsList;
 
 #[derive(Routable, PartialEq, Clone, Debug)]
 pub enum Route {
     #[at("/posts/:id")]
     ShowPost { id: u64 },
+    #[at("/p/:slug")]
+    ShowPostBySlug { slug: String },
     #[at("/posts/compose/:id")]
     ComposePost { id: u64 },
     #[at("/posts/tag/:tag_name")]
     ListPostsByTag { tag_name: String },
+    #[at("/series/:name")]
+    ListPostsBySeries { name: String },
+    #[at("/archive")]
+    Archive,
+    #[at("/archive/:year/:month")]
+    ArchiveMonth { year: i32, month: u32 },
     #[at("/tags")]
     Tags,
+    #[at("/search")]
+    Search,
     #[at("/about")]
     About,
     #[at("/")]
@@ -23,6 +35,14 @@ pub enum Route {
     NotFound,
 }
 
+// 有 slug 的博客优先使用可读的永久链接
+pub fn post_route(id: i64, slug: &Option<String>) -> Route {
+    match slug {
+        Some(slug) if !slug.is_empty() => Route::ShowPostBySlug { slug: slug.clone() },
+        _ => Route::ShowPost { id: id as u64 },
+    }
+}
+
 #[function_component(About)]
 fn about() -> Html {
     html! {
@@ -102,18 +122,33 @@ pub fn switch(routes: Route) -> Html {
         Route::ShowPost { id } => {
             html! { <PostDetail post_id={id} /> }
         },
+        Route::ShowPostBySlug { slug } => {
+            html! { <PostDetail post_id={0} {slug} /> }
+        },
         Route::ListPostsByTag { tag_name } => {
             html! { <PostsListByTag tag_name={String::from(tag_name)} /> }
         },
+        Route::ListPostsBySeries { name } => {
+            html! { <PostsListBySeries {name} /> }
+        },
         Route::ListPosts => {
             html! { <PostsList /> }
         },
         Route::ComposePost { id } => {
             html! { <PostCompose post_id={id} /> }
         },
+        Route::Archive => {
+            html! { <PostsArchive /> }
+        },
+        Route::ArchiveMonth { year, month } => {
+            html! { <PostsArchive year={Some(year)} month={Some(month)} /> }
+        },
         Route::Tags => {
             html! { <TagsList /> }
         },
+        Route::Search => {
+            html! { <PostsSearch /> }
+        },
         Route::About => {
             html! { <About /> }
         },